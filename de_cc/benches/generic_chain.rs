@@ -0,0 +1,82 @@
+//! Times `compile` on deep chains of type variables: generic functions that
+//! each call the next one, and variables that are each initialized with the
+//! one before them.
+//!
+//! Run with `cargo bench --bench generic_chain > /dev/null`, timings are
+//! printed to stderr.
+
+use std::time::Instant;
+
+use de_cc::{
+    compile,
+    language::parsed::{
+        constructors::*, declaration::constructors::*, expression::constructors::*, Application,
+        File, Node,
+    },
+    type_system::type_info::constructors::*,
+    Session,
+};
+
+fn func_name(func: usize) -> String {
+    format!("f_{}", func)
+}
+
+fn var_name(var: usize) -> String {
+    format!("x_{}", var)
+}
+
+/// Generates a chain of `num_funcs` generic functions, where every function
+/// passes its argument on to the next one, and a `main` function that declares
+/// `num_vars` variables, where every variable is initialized with the variable
+/// before it.
+fn generate_application(num_funcs: usize, num_vars: usize) -> Application {
+    let mut nodes = (0..num_funcs)
+        .map(|i| {
+            let body = if i + 1 < num_funcs {
+                func_app(&func_name(i + 1), &[], &[var("x")])
+            } else {
+                var("x")
+            };
+            func_decl(
+                &func_name(i),
+                &[type_param("T", None)],
+                &[func_param("x", t_gen_("T"))],
+                &[return_(body)],
+                t_gen_("T"),
+            )
+        })
+        .collect::<Vec<Node>>();
+
+    let mut body = vec![var_decl(&var_name(0), None, u64(1u64))];
+    for i in 1..num_vars {
+        body.push(var_decl(&var_name(i), None, var(&var_name(i - 1))));
+    }
+    body.push(var_decl(
+        "y",
+        None,
+        func_app(&func_name(0), &[], &[var(&var_name(num_vars - 1))]),
+    ));
+    nodes.push(func_decl("main", &[], &[], &body, t_unit()));
+
+    Application {
+        files: vec![File {
+            name: "main.sw".to_string(),
+            nodes,
+        }],
+    }
+}
+
+fn main() {
+    for (num_funcs, num_vars) in [(10, 100), (50, 500), (100, 1000)] {
+        let session = Session::new();
+        let application = session.enter(|| generate_application(num_funcs, num_vars));
+        let start = Instant::now();
+        compile(&session, application).unwrap();
+        eprintln!(
+            "{} generic functions, {} variables: {:?}",
+            num_funcs,
+            num_vars,
+            start.elapsed()
+        );
+    }
+}
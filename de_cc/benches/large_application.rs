@@ -0,0 +1,76 @@
+//! Times `compile` on large generated applications where every function body
+//! looks up a function from its own file or from a star imported file.
+//!
+//! Run with `cargo bench --bench large_application > /dev/null`, timings are
+//! printed to stderr.
+
+use std::time::Instant;
+
+use de_cc::{
+    compile,
+    language::parsed::{
+        constructors::*, declaration::constructors::*, expression::constructors::*, Application,
+        File,
+    },
+    type_system::type_info::constructors::*,
+    Session,
+};
+
+fn func_name(file: usize, func: usize) -> String {
+    format!("f_{}_{}", file, func)
+}
+
+/// Generates an application with `num_files` files of `num_funcs` functions each.
+/// Every file star imports the file before it, and every function calls the
+/// function before it, crossing into the imported file for the first function.
+fn generate_application(num_files: usize, num_funcs: usize) -> Application {
+    let files = (0..num_files)
+        .map(|i| {
+            let mut nodes = vec![];
+            if i > 0 {
+                nodes.push(star_import(&format!("file_{}.sw", i - 1)));
+            }
+            for j in 0..num_funcs {
+                let callee = match (i, j) {
+                    (0, 0) => None,
+                    (_, 0) => Some(func_name(i - 1, num_funcs - 1)),
+                    (_, _) => Some(func_name(i, j - 1)),
+                };
+                let body = match callee {
+                    Some(callee) => vec![
+                        var_decl("y", None, func_app(&callee, &[], &[var("x")])),
+                        return_(var("y")),
+                    ],
+                    None => vec![return_(var("x"))],
+                };
+                nodes.push(func_decl(
+                    &func_name(i, j),
+                    &[],
+                    &[func_param("x", t_u64())],
+                    &body,
+                    t_u64(),
+                ));
+            }
+            File {
+                name: format!("file_{}.sw", i),
+                nodes,
+            }
+        })
+        .collect::<Vec<_>>();
+    Application { files }
+}
+
+fn main() {
+    for (num_files, num_funcs) in [(4, 25), (8, 50), (16, 100), (1, 1000)] {
+        let session = Session::new();
+        let application = session.enter(|| generate_application(num_files, num_funcs));
+        let start = Instant::now();
+        compile(&session, application).unwrap();
+        eprintln!(
+            "{} files x {} functions: {:?}",
+            num_files,
+            num_funcs,
+            start.elapsed()
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Times `compile` on generated applications with large function bodies, where
+//! every function calls the functions before it many times, so declarations
+//! are looked up far more often than they are declared.
+//!
+//! Run with `cargo bench --bench large_bodies > /dev/null`, timings are printed
+//! to stderr.
+
+use std::time::Instant;
+
+use de_cc::{
+    compile,
+    language::parsed::{
+        constructors::*, declaration::constructors::*, expression::constructors::*, Application,
+        File,
+    },
+    type_system::type_info::constructors::*,
+    Session,
+};
+
+fn func_name(func: usize) -> String {
+    format!("f_{}", func)
+}
+
+/// Generates an application with one file of `num_funcs` functions, whose
+/// bodies have `body_len` variable declarations each. Every variable calls one
+/// of the functions declared before it.
+fn generate_application(num_funcs: usize, body_len: usize) -> Application {
+    let nodes = (0..num_funcs)
+        .map(|i| {
+            let mut body = vec![];
+            let mut last = "x".to_string();
+            if i > 0 {
+                for j in 0..body_len {
+                    let name = format!("y_{}", j);
+                    body.push(var_decl(
+                        &name,
+                        None,
+                        func_app(&func_name(j % i), &[], &[var(&last)]),
+                    ));
+                    last = name;
+                }
+            }
+            body.push(return_(var(&last)));
+            func_decl(
+                &func_name(i),
+                &[],
+                &[func_param("x", t_u64())],
+                &body,
+                t_u64(),
+            )
+        })
+        .collect::<Vec<_>>();
+    Application {
+        files: vec![File {
+            name: "bodies.sw".to_string(),
+            nodes,
+        }],
+    }
+}
+
+fn main() {
+    for (num_funcs, body_len) in [(50, 50), (100, 100), (200, 200)] {
+        let session = Session::new();
+        let application = session.enter(|| generate_application(num_funcs, body_len));
+        let start = Instant::now();
+        compile(&session, application).unwrap();
+        eprintln!(
+            "{} functions x {} variables: {:?}",
+            num_funcs,
+            body_len,
+            start.elapsed()
+        );
+    }
+}
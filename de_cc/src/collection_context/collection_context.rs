@@ -1,24 +1,33 @@
-use std::{collections::HashMap, ops::Index};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Index,
+};
 
 use petgraph::{
     dot::{Config, Dot},
     prelude::EdgeIndex,
+    visit::EdgeRef,
+    Direction,
 };
 
-use crate::declaration_engine::declaration_id::DeclarationId;
+use crate::{
+    declaration_engine::declaration_id::DeclarationId,
+    language::ty::typed_declaration::TyVariableDeclaration, session::Session,
+};
 
 use super::{
     bfs,
     collection_edge::CollectionEdge,
     collection_index::{CCIdx, CollectionIndex},
     collection_node::CollectionNode,
+    symbol_table::{build_symbol_tables, SymbolTable},
     CollectionGraph,
 };
 
 /// Represents a typeable AST as a graph by adding typeable AST nodes into a graph
 /// and by drawing edges between relevant AST nodes. An edge is created between two
-/// AST nodes when those two AST nodes share some scope relationship---sharing the
-/// same scope, one being a scoped child of another, etc.
+/// AST nodes when those two AST nodes share some scope relationship---one being a
+/// scoped child of another, one being declared directly before another, etc.
 ///
 /// At a high level, the [CollectionContext] is a cyclical directed graph that allows
 /// for both parallel edges and for self-referential edges. This is useful for the
@@ -31,6 +40,11 @@ use super::{
 /// outward in scope to the file its in. In this way, the root of the graph is the
 /// entire application itself, as it encompasses the most outward scope.
 ///
+/// AST nodes that share a scope are not connected to each other directly. Instead,
+/// they each have a single edge to the node that owns that scope (the file, code
+/// block, trait, or trait impl that they are in), so a scope with n AST nodes only
+/// adds n edges to the graph.
+///
 /// Modeling scope in this way is useful because it allows us to 'look ahead' at
 /// 'future AST nodes' during type collection, type inference, etc. We can think of it
 /// this way. Given this file:
@@ -52,10 +66,18 @@ use super::{
 /// an ordering for which to do type inference. The [CollectionContext] gives the ability
 /// for the compiler to either 'look forward' or 'look backward' in the AST given any
 /// location where type inference is currently 'standing'.
+///
+/// Variables, on the other hand, are only visible after they are declared. To model
+/// this, every AST node in a scope has a [CollectionEdge::SequentialScope] edge
+/// pointing to the AST node directly before it, and the first AST node in a function
+/// body points to the last parameter of that function. Variable lookup only follows
+/// these edges.
 #[derive(Default, Clone)]
 pub(crate) struct CollectionContext {
     pub(super) graph: CollectionGraph,
     pub(super) files: HashMap<String, CollectionIndex>,
+    symbol_tables: HashMap<CollectionIndex, SymbolTable>,
+    scopes: HashMap<CollectionIndex, CollectionIndex>,
 }
 
 impl CollectionContext {
@@ -79,6 +101,10 @@ impl CollectionContext {
         self.files.insert(filename, index);
     }
 
+    pub(crate) fn unregister_file_index(&mut self, filename: &str) {
+        self.files.remove(filename);
+    }
+
     pub(crate) fn get_file_index(&self, filename: String) -> Result<CollectionIndex, String> {
         self.files
             .get(&filename)
@@ -103,17 +129,109 @@ impl CollectionContext {
         self.graph.add_edge(*from, *to, edge)
     }
 
+    /// Removes every edge pointing out of the node at `index`, detaching it from
+    /// the scope that it was declared in and from the node declared before it.
+    /// Used to move a node into a new scope.
+    pub(crate) fn detach(&mut self, index: CollectionIndex) {
+        let edges = self
+            .graph
+            .edges_directed(*index, Direction::Outgoing)
+            .map(|edge| edge.id())
+            .collect::<Vec<_>>();
+        for edge in edges.into_iter() {
+            self.graph.remove_edge(edge);
+        }
+    }
+
+    /// Removes the node at `index` and every node nested in it, which are the
+    /// nodes that have a path of edges to it, and returns the removed nodes.
+    /// Nodes that were moved out with [CollectionContext::detach] are kept.
+    pub(crate) fn remove_tree(
+        &mut self,
+        index: CollectionIndex,
+    ) -> Vec<(CollectionIndex, CollectionNode)> {
+        let mut found = HashSet::from([*index]);
+        let mut nested = vec![*index];
+        let mut next = 0;
+        while next < nested.len() {
+            for child in self
+                .graph
+                .neighbors_directed(nested[next], Direction::Incoming)
+            {
+                if found.insert(child) {
+                    nested.push(child);
+                }
+            }
+            next += 1;
+        }
+        nested
+            .into_iter()
+            .filter_map(|node| {
+                let removed = self.graph.remove_node(node)?;
+                Some((CollectionIndex::new(node), removed))
+            })
+            .collect()
+    }
+
+    /// The number of nodes in the graph.
+    #[allow(dead_code)]
+    pub(crate) fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Builds the [SymbolTable]s used by [CollectionContext::get_symbol]. This
+    /// must be called once the graph is complete, and fails if a scope declares
+    /// the same name more than once.
+    pub(crate) fn build_symbol_tables(&mut self, session: &Session) -> Result<(), String> {
+        let (symbol_tables, scopes) = build_symbol_tables(session, &self.graph)?;
+        self.symbol_tables = symbol_tables;
+        self.scopes = scopes;
+        Ok(())
+    }
+
+    /// Looks up the declaration `symbol` as seen from the node at `index`, by
+    /// searching the scopes enclosing `index` from the innermost outwards. In each
+    /// scope, declarations in that scope take precedence over star imports.
     pub(crate) fn get_symbol(
         &self,
         index: CollectionIndex,
         symbol: &str,
     ) -> Result<CCIdx<DeclarationId>, String> {
-        let decls_in_scope = bfs::get_all_declarations_in_scope(self, index)?;
-        for (name, decl_id) in decls_in_scope.into_iter() {
-            if name == symbol {
-                return Ok(decl_id);
+        let mut scope = self.scopes.get(&index).cloned();
+        while let Some(scope_index) = scope {
+            let table = self
+                .symbol_tables
+                .get(&scope_index)
+                .ok_or_else(|| "scope has no symbol table".to_string())?;
+            if let Some(decl_id) = table.get_local(symbol) {
+                return Ok(decl_id.clone());
+            }
+            if let Some(decl_id) = table.get_imported(symbol, |filename| {
+                self.get_file_symbol_table(filename.to_string())
+            })? {
+                return Ok(decl_id.clone());
             }
+            scope = table.parent;
         }
         Err(format!("symbol {} not found in scope", symbol))
     }
+
+    fn get_file_symbol_table(&self, filename: String) -> Result<&SymbolTable, String> {
+        let file_index = self.get_file_index(filename)?;
+        self.symbol_tables
+            .get(&file_index)
+            .ok_or_else(|| "file has no symbol table".to_string())
+    }
+
+    /// Looks up the variable `symbol` as seen from the node at `index`. Unlike
+    /// [CollectionContext::get_symbol], this lookup is order-sensitive and only
+    /// finds variables declared before `index` in the same sequential scope.
+    pub(crate) fn get_variable(
+        &self,
+        index: CollectionIndex,
+        symbol: &str,
+    ) -> Result<TyVariableDeclaration, String> {
+        bfs::get_variable_in_sequential_scope(self, index, symbol)
+            .ok_or_else(|| format!("variable {} not found in scope", symbol))
+    }
 }
@@ -4,8 +4,8 @@ use std::fmt;
 pub(crate) enum CollectionEdge {
     ApplicationContents,
     FileContents,
-    SharedScope,
     ScopedChild,
+    SequentialScope,
 }
 
 impl fmt::Display for CollectionEdge {
@@ -13,8 +13,8 @@ impl fmt::Display for CollectionEdge {
         match self {
             CollectionEdge::ApplicationContents => write!(f, "application contents"),
             CollectionEdge::FileContents => write!(f, "file contents"),
-            CollectionEdge::SharedScope => write!(f, "shared scope"),
             CollectionEdge::ScopedChild => write!(f, "scoped child"),
+            CollectionEdge::SequentialScope => write!(f, "sequential scope"),
         }
     }
 }
@@ -1,9 +1,14 @@
 use std::fmt;
 
-use itertools::Itertools;
 use petgraph::prelude::{EdgeIndex, NodeIndex};
 
 use crate::{
+    declaration_engine::{
+        declaration_engine::de_expect_decl_id,
+        declaration_id::{DeclId, DeclarationId},
+        declaration_wrapper::Declaration,
+    },
+    session::Session,
     type_system::type_mapping::TypeMapping,
     types::{copy_types::CopyTypes, pretty_print::PrettyPrint},
 };
@@ -38,8 +43,8 @@ impl<T> CopyTypes for CCIdx<T>
 where
     T: CopyTypes,
 {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.inner.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.inner.copy_types(session, type_mapping);
     }
 }
 
@@ -69,10 +74,6 @@ impl<T> CCIdx<T> {
         &self.inner
     }
 
-    pub(crate) fn inner_ref_mut(&mut self) -> &mut T {
-        &mut self.inner
-    }
-
     pub(crate) fn add_edge<F>(
         from: &CCIdx<T>,
         to: &CCIdx<F>,
@@ -93,22 +94,40 @@ impl<T> CCIdx<T> {
         });
     }
 
-    pub(crate) fn add_edges_many(
+    /// Adds an edge from every node to the node directly before it.
+    pub(crate) fn add_edges_sequential(
         nodes: &[CCIdx<T>],
         edge: CollectionEdge,
         cc: &mut CollectionContext,
-    ) where
-        T: fmt::Debug,
-    {
-        nodes.iter().permutations(2).for_each(|inner_nodes| {
-            let a = inner_nodes[0];
-            let b = inner_nodes[1];
-            CCIdx::add_edge(a, b, edge.clone(), cc);
+    ) {
+        nodes.windows(2).for_each(|window| {
+            CCIdx::add_edge(&window[1], &window[0], edge.clone(), cc);
         });
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+impl<T> CCIdx<DeclId<T>> {
+    /// The same declaration, without the kind of declaration that it is.
+    pub(crate) fn untyped(&self) -> CCIdx<DeclarationId> {
+        CCIdx::new(self.inner.untyped(), self.idx)
+    }
+}
+
+impl CCIdx<DeclarationId> {
+    /// Checks that this is a declaration of type `T`, such as a
+    /// [TyFunctionDeclaration](crate::language::ty::typed_declaration::TyFunctionDeclaration).
+    pub(crate) fn expect<T>(&self, session: &Session) -> Result<CCIdx<DeclId<T>>, String>
+    where
+        T: Declaration,
+    {
+        Ok(CCIdx::new(
+            de_expect_decl_id(session, self.inner)?,
+            self.idx,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct CollectionIndex(NodeIndex);
 
 impl std::ops::Deref for CollectionIndex {
@@ -1,9 +1,12 @@
 use std::fmt;
 
 use crate::{
-    declaration_engine::declaration_id::DeclarationId,
+    declaration_engine::declaration_id::{DeclId, DeclarationId},
     language::ty::{
-        typed_declaration::{TyCodeBlock, TyVariableDeclaration},
+        typed_declaration::{
+            TyCodeBlock, TyFunctionDeclaration, TyStructDeclaration, TyTraitDeclaration, TyTraitFn,
+            TyTraitImpl, TyVariableDeclaration,
+        },
         typed_expression::TyExpression,
         TyApplication, TyFile,
     },
@@ -12,17 +15,39 @@ use crate::{
 #[derive(Clone)]
 pub(crate) enum CollectionNode {
     StarImport(String),
+    #[allow(dead_code)]
     Application(TyApplication),
     File(TyFile),
     Expression(TyExpression),
     Return(TyExpression),
     Variable(String, TyVariableDeclaration),
-    Function(String, DeclarationId),
+    Function(String, DeclId<TyFunctionDeclaration>),
     CodeBlock(TyCodeBlock),
-    Trait(String, DeclarationId),
-    TraitFn(String, DeclarationId),
-    TraitImpl(String, DeclarationId),
-    Struct(String, DeclarationId),
+    Trait(String, DeclId<TyTraitDeclaration>),
+    TraitFn(String, DeclId<TyTraitFn>),
+    #[allow(dead_code)]
+    TraitImpl(String, DeclId<TyTraitImpl>),
+    Struct(String, DeclId<TyStructDeclaration>),
+}
+
+impl CollectionNode {
+    /// The declaration that this node declares, if any.
+    pub(crate) fn declaration_id(&self) -> Option<DeclarationId> {
+        match self {
+            CollectionNode::Function(_, decl_id) => Some(decl_id.untyped()),
+            CollectionNode::Trait(_, decl_id) => Some(decl_id.untyped()),
+            CollectionNode::TraitFn(_, decl_id) => Some(decl_id.untyped()),
+            CollectionNode::TraitImpl(_, decl_id) => Some(decl_id.untyped()),
+            CollectionNode::Struct(_, decl_id) => Some(decl_id.untyped()),
+            CollectionNode::StarImport(_)
+            | CollectionNode::Application(_)
+            | CollectionNode::File(_)
+            | CollectionNode::Expression(_)
+            | CollectionNode::Return(_)
+            | CollectionNode::Variable(_, _)
+            | CollectionNode::CodeBlock(_) => None,
+        }
+    }
 }
 
 impl fmt::Debug for CollectionNode {
@@ -6,7 +6,8 @@ pub(crate) mod collection_context;
 pub(crate) mod collection_edge;
 pub(crate) mod collection_index;
 pub(crate) mod collection_node;
+mod symbol_table;
 
 use self::{collection_edge::CollectionEdge, collection_node::CollectionNode};
 
-type CollectionGraph = petgraph::Graph<CollectionNode, CollectionEdge>;
+type CollectionGraph = petgraph::stable_graph::StableGraph<CollectionNode, CollectionEdge>;
@@ -0,0 +1,245 @@
+use std::{cell::OnceCell, collections::HashMap, ops::Index};
+
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+
+use crate::{
+    declaration_engine::{
+        declaration_engine::{de_get_struct, de_get_trait, de_get_trait_impl},
+        declaration_id::DeclarationId,
+    },
+    session::Session,
+};
+
+use super::{
+    collection_edge::CollectionEdge,
+    collection_index::{CCIdx, CollectionIndex},
+    collection_node::CollectionNode,
+    CollectionGraph,
+};
+
+type Symbols = HashMap<String, CCIdx<DeclarationId>>;
+
+/// The declarations that are declared directly inside of a scope node in the
+/// [CollectionContext](super::collection_context::CollectionContext). A scope node
+/// is any node that other nodes point to with a [CollectionEdge::ScopedChild] or
+/// [CollectionEdge::FileContents] edge---files, code blocks, functions, traits, and
+/// trait impls.
+///
+/// Star imports are stored by file name and are only resolved into declarations
+/// the first time that a lookup falls through to them.
+#[derive(Default, Clone)]
+pub(crate) struct SymbolTable {
+    /// The scope that this scope node is itself declared in.
+    pub(super) parent: Option<CollectionIndex>,
+    symbols: Symbols,
+    star_imports: Vec<String>,
+    imported_symbols: OnceCell<Result<Symbols, String>>,
+}
+
+impl SymbolTable {
+    pub(super) fn get_local(&self, symbol: &str) -> Option<&CCIdx<DeclarationId>> {
+        self.symbols.get(symbol)
+    }
+
+    /// Looks up `symbol` in the files imported into this scope. `resolve_file`
+    /// is used to find the declarations of an imported file, and is only called
+    /// the first time that this scope's imports are needed.
+    pub(super) fn get_imported<'a, F>(
+        &self,
+        symbol: &str,
+        resolve_file: F,
+    ) -> Result<Option<&CCIdx<DeclarationId>>, String>
+    where
+        F: Fn(&str) -> Result<&'a SymbolTable, String>,
+    {
+        let imported_symbols = self.imported_symbols.get_or_init(|| {
+            let mut imported_symbols = Symbols::new();
+            for filename in self.star_imports.iter() {
+                for (name, decl_id) in resolve_file(filename)?.symbols.iter() {
+                    imported_symbols
+                        .entry(name.clone())
+                        .or_insert_with(|| decl_id.clone());
+                }
+            }
+            Ok(imported_symbols)
+        });
+        match imported_symbols {
+            Ok(imported_symbols) => Ok(imported_symbols.get(symbol)),
+            Err(e) => Err(e.clone()),
+        }
+    }
+}
+
+type SymbolTables = HashMap<CollectionIndex, SymbolTable>;
+type Scopes = HashMap<CollectionIndex, CollectionIndex>;
+
+/// Builds a [SymbolTable] for every scope node in `graph`, keyed by the index of
+/// the scope node. Also returns the scope that every node in `graph` belongs to.
+///
+/// Returns an error if two declarations in the same scope, or two fields of the
+/// same struct, have the same name.
+pub(super) fn build_symbol_tables(
+    session: &Session,
+    graph: &CollectionGraph,
+) -> Result<(SymbolTables, Scopes), String> {
+    let mut symbol_tables: SymbolTables = HashMap::new();
+    let mut scopes = HashMap::new();
+    let mut duplicate = None;
+
+    for edge in graph.edge_references() {
+        match edge.weight() {
+            CollectionEdge::FileContents | CollectionEdge::ScopedChild => {}
+            CollectionEdge::ApplicationContents | CollectionEdge::SequentialScope => continue,
+        }
+
+        let child = CollectionIndex::new(edge.source());
+        let scope = CollectionIndex::new(edge.target());
+        scopes.insert(child, scope);
+
+        let table = symbol_tables.entry(scope).or_default();
+        match graph.index(edge.source()) {
+            CollectionNode::StarImport(filename) => {
+                table.star_imports.push(filename.clone());
+            }
+            node => {
+                let (name, decl_id) = match node {
+                    CollectionNode::Function(name, decl_id) => (name, decl_id.untyped()),
+                    CollectionNode::Trait(name, decl_id) => (name, decl_id.untyped()),
+                    CollectionNode::TraitFn(name, decl_id) => (name, decl_id.untyped()),
+                    CollectionNode::Struct(name, decl_id) => (name, decl_id.untyped()),
+                    _ => continue,
+                };
+                match table.symbols.get(name) {
+                    Some(first) => {
+                        duplicate.get_or_insert((scope, first.idx(), child));
+                    }
+                    None => {
+                        table
+                            .symbols
+                            .insert(name.clone(), CCIdx::new(decl_id, child));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some((scope, first, second)) = duplicate {
+        return Err(format!(
+            "`{}` is defined more than once in {}, as the {} and as the {}",
+            name_of(graph, first),
+            scope_path(graph, &scopes, scope),
+            describe_site(session, graph, scope, first),
+            describe_site(session, graph, scope, second),
+        ));
+    }
+
+    for index in graph.node_indices() {
+        if let CollectionNode::Struct(name, decl_id) = graph.index(index) {
+            let struct_decl = de_get_struct(session, *decl_id)?;
+            for (i, field) in struct_decl.fields.iter().enumerate() {
+                if let Some(j) = struct_decl.fields[..i]
+                    .iter()
+                    .position(|other| other.name == field.name)
+                {
+                    let index = CollectionIndex::new(index);
+                    let path = match scopes.get(&index) {
+                        Some(scope) => format!("{}::{}", scope_path(graph, &scopes, *scope), name),
+                        None => name.clone(),
+                    };
+                    return Err(format!(
+                        "field `{}` is defined more than once in struct {}, as field {} and as field {}",
+                        field.name,
+                        path,
+                        j + 1,
+                        i + 1
+                    ));
+                }
+            }
+        }
+    }
+
+    for (scope, table) in symbol_tables.iter_mut() {
+        table.parent = scopes.get(scope).cloned();
+    }
+
+    Ok((symbol_tables, scopes))
+}
+
+fn name_of(graph: &CollectionGraph, index: CollectionIndex) -> &str {
+    match graph.index(*index) {
+        CollectionNode::Function(name, _)
+        | CollectionNode::Trait(name, _)
+        | CollectionNode::TraitFn(name, _)
+        | CollectionNode::TraitImpl(name, _)
+        | CollectionNode::Struct(name, _)
+        | CollectionNode::Variable(name, _) => name,
+        CollectionNode::File(file) => &file.name,
+        _ => "",
+    }
+}
+
+/// The path of the named scopes enclosing and including `scope`, e.g.
+/// `bob.sw::main` for the body of the function `main` in `bob.sw`.
+fn scope_path(graph: &CollectionGraph, scopes: &Scopes, scope: CollectionIndex) -> String {
+    let mut path = vec![];
+    let mut current = Some(scope);
+    while let Some(index) = current {
+        match graph.index(*index) {
+            CollectionNode::File(_)
+            | CollectionNode::Function(..)
+            | CollectionNode::Trait(..)
+            | CollectionNode::TraitImpl(..)
+            | CollectionNode::Struct(..) => path.push(name_of(graph, index)),
+            _ => {}
+        }
+        current = scopes.get(&index).cloned();
+    }
+    path.reverse();
+    path.join("::")
+}
+
+/// Describes the declaration `child` by its kind and its position in `scope`,
+/// e.g. `function at item 3`.
+fn describe_site(
+    session: &Session,
+    graph: &CollectionGraph,
+    scope: CollectionIndex,
+    child: CollectionIndex,
+) -> String {
+    let kind = match graph.index(*child) {
+        CollectionNode::Function(..) => "function",
+        CollectionNode::Trait(..) => "trait",
+        CollectionNode::TraitFn(..) => "trait fn",
+        CollectionNode::Struct(..) => "struct",
+        _ => "declaration",
+    };
+    let siblings = match graph.index(*scope) {
+        CollectionNode::File(file) => file.nodes.iter().map(|node| node.idx()).collect(),
+        CollectionNode::CodeBlock(code_block) => {
+            code_block.contents.iter().map(|node| node.idx()).collect()
+        }
+        CollectionNode::Trait(_, decl_id) => de_get_trait(session, *decl_id)
+            .map(|trait_decl| {
+                trait_decl
+                    .interface_surface
+                    .iter()
+                    .map(|trait_fn| trait_fn.idx())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        CollectionNode::TraitImpl(_, decl_id) => de_get_trait_impl(session, *decl_id)
+            .map(|trait_impl| {
+                trait_impl
+                    .methods
+                    .iter()
+                    .map(|method| method.idx())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+    match siblings.iter().position(|sibling| *sibling == child) {
+        Some(position) => format!("{} at item {}", kind, position + 1),
+        None => kind.to_string(),
+    }
+}
@@ -1,8 +1,75 @@
-use std::{fmt, sync::RwLock};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
 
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The number of slabs created so far, used to tell slabs apart in debug builds.
+#[cfg(debug_assertions)]
+static NUM_SLABS: AtomicU32 = AtomicU32::new(0);
+
+/// An index into a [ConcurrentSlab], tagged with the generation of the slot
+/// that it was handed out for.
+///
+/// Clearing a slab starts a new generation, and so does removing a value from
+/// its slot, so an index that outlives a [ConcurrentSlab::clear] or a
+/// [ConcurrentSlab::remove] is recognized as stale instead of silently referring
+/// to whatever was inserted at the same position afterwards. Debug builds also
+/// tag the index with the slab that handed it out, so that an index used with a
+/// different slab is caught too.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct SlabIndex {
+    index: usize,
+    generation: u32,
+    #[cfg(debug_assertions)]
+    slab: u32,
+}
+
+impl SlabIndex {
+    /// The index `index` into the same slab, in the same generation, as `self`.
+    ///
+    /// Only slabs that never remove values keep every slot in the same
+    /// generation, so this is not meant for any other slab.
+    pub(crate) fn with_index(self, index: usize) -> SlabIndex {
+        SlabIndex { index, ..self }
+    }
+}
+
+impl std::ops::Deref for SlabIndex {
+    type Target = usize;
+    fn deref(&self) -> &Self::Target {
+        &self.index
+    }
+}
+
+/// A slab that can be shared between threads.
+///
+/// Values are stored behind an [Arc], so that getting a value from the slab
+/// only clones a pointer to it, and the value stays valid after it is replaced.
+/// The slots of removed values are reused by later insertions.
 #[derive(Debug)]
 pub(crate) struct ConcurrentSlab<T> {
-    inner: RwLock<Vec<T>>,
+    inner: RwLock<Inner<T>>,
+    #[cfg(debug_assertions)]
+    id: u32,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    slots: Vec<Slot<T>>,
+    /// The slots whose values have been removed.
+    free: Vec<usize>,
+    /// The generation that new slots start in, which is newer than the
+    /// generation of any slot from before the last [ConcurrentSlab::clear].
+    generation: u32,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    value: Option<Arc<T>>,
+    generation: u32,
 }
 
 impl<T> Default for ConcurrentSlab<T>
@@ -11,14 +78,20 @@ where
 {
     fn default() -> Self {
         Self {
-            inner: Default::default(),
+            inner: RwLock::new(Inner {
+                slots: Vec::new(),
+                free: Vec::new(),
+                generation: 0,
+            }),
+            #[cfg(debug_assertions)]
+            id: NUM_SLABS.fetch_add(1, Ordering::Relaxed),
         }
     }
 }
 
 impl<T> ConcurrentSlab<T>
 where
-    T: Clone + PartialEq,
+    T: PartialEq,
 {
     #[allow(dead_code)]
     pub(crate) fn debug_print(&self)
@@ -26,24 +99,62 @@ where
         T: fmt::Debug,
     {
         let inner = self.inner.read().unwrap();
-        inner.iter().enumerate().for_each(|(i, elem)| {
-            println!("{} -> {:?}", i, elem);
+        inner.slots.iter().enumerate().for_each(|(i, slot)| {
+            if let Some(elem) = &slot.value {
+                println!("{} -> {:?}", i, elem);
+            }
         })
     }
 
-    pub(crate) fn insert(&self, value: T) -> usize {
+    /// The number of values in the slab.
+    #[allow(dead_code)]
+    pub(crate) fn len(&self) -> usize {
+        let inner = self.inner.read().unwrap();
+        inner.slots.len() - inner.free.len()
+    }
+
+    pub(crate) fn insert(&self, value: T) -> SlabIndex {
         let mut inner = self.inner.write().unwrap();
-        let ret = inner.len();
-        inner.push(value);
-        ret
+        let index = match inner.free.pop() {
+            Some(index) => {
+                inner.slots[index].value = Some(Arc::new(value));
+                index
+            }
+            None => {
+                let generation = inner.generation;
+                inner.slots.push(Slot {
+                    value: Some(Arc::new(value)),
+                    generation,
+                });
+                inner.slots.len() - 1
+            }
+        };
+        SlabIndex {
+            index,
+            generation: inner.slots[index].generation,
+            #[cfg(debug_assertions)]
+            slab: self.id,
+        }
     }
 
-    pub(crate) fn get(&self, index: usize) -> T {
+    /// Returns an error if `index` is stale, or was handed out by another slab.
+    pub(crate) fn get(&self, index: SlabIndex) -> Result<Arc<T>, String> {
         let inner = self.inner.read().unwrap();
-        inner[index].clone()
+        self.check(&inner, index).cloned()
     }
 
-    pub(crate) fn replace(&self, index: usize, prev_value: &T, new_value: T) -> Option<T> {
+    /// Returns the error of [ConcurrentSlab::get] if `index` is stale.
+    pub(crate) fn validate(&self, index: SlabIndex) -> Result<(), String> {
+        let inner = self.inner.read().unwrap();
+        self.check(&inner, index).map(|_| ())
+    }
+
+    pub(crate) fn replace(
+        &self,
+        index: SlabIndex,
+        prev_value: &T,
+        new_value: Arc<T>,
+    ) -> Result<Option<Arc<T>>, String> {
         // The comparison below ends up calling functions in the slab, which
         // can lead to deadlocks if we used a single read/write lock.
         // So we split the operation: we do the read only operations with
@@ -51,25 +162,131 @@ where
         // we get a write lock for writing into the slab.
         {
             let inner = self.inner.read().unwrap();
-            let actual_prev_value = &inner[index];
-            if actual_prev_value != prev_value {
-                return Some(actual_prev_value.clone());
+            let actual_prev_value = self.check(&inner, index)?;
+            if **actual_prev_value != *prev_value {
+                return Ok(Some(actual_prev_value.clone()));
             }
         }
 
         let mut inner = self.inner.write().unwrap();
-        inner[index] = new_value;
-        None
+        inner.slots[*index].value = Some(new_value);
+        Ok(None)
+    }
+
+    /// Overwrites the value at `index` without comparing it to the previous
+    /// value, which is used to undo a [ConcurrentSlab::replace].
+    pub(crate) fn set(&self, index: SlabIndex, value: Arc<T>) -> Result<(), String> {
+        let mut inner = self.inner.write().unwrap();
+        self.check(&inner, index)?;
+        inner.slots[*index].value = Some(value);
+        Ok(())
     }
 
+    /// Removes the value at `index`, which makes `index` stale. Its slot is
+    /// reused by a later insertion, under a new generation.
+    pub(crate) fn remove(&self, index: SlabIndex) -> Result<(), String> {
+        let mut inner = self.inner.write().unwrap();
+        self.check(&inner, index)?;
+        let slot = &mut inner.slots[*index];
+        slot.value = None;
+        slot.generation += 1;
+        inner.free.push(*index);
+        Ok(())
+    }
+
+    /// Removes every value from the slab and starts a new generation, which
+    /// makes every index handed out so far stale.
     pub(crate) fn clear(&self) {
         let mut inner = self.inner.write().unwrap();
-        *inner = Vec::new();
+        let newest = inner.slots.iter().map(|slot| slot.generation).max();
+        inner.generation = newest.unwrap_or(inner.generation).max(inner.generation) + 1;
+        inner.slots = Vec::new();
+        inner.free = Vec::new();
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn exists<F: Fn(&T) -> bool>(&self, f: F) -> bool {
-        let inner = self.inner.read().unwrap();
-        inner.iter().any(f)
+    /// Returns the value at `index`, or an error if `index` is stale or was
+    /// handed out by another slab.
+    fn check<'a>(&self, inner: &'a Inner<T>, index: SlabIndex) -> Result<&'a Arc<T>, String> {
+        #[cfg(debug_assertions)]
+        if index.slab != self.id {
+            return Err(format!(
+                "id {} was created by engine #{} but used with engine #{}",
+                index.index, index.slab, self.id
+            ));
+        }
+        if index.generation < inner.generation {
+            return Err(format!(
+                "stale id {}: it was created in generation {} of its engine, which has since been cleared and is now in generation {}",
+                index.index, index.generation, inner.generation
+            ));
+        }
+        match inner.slots.get(index.index) {
+            Some(Slot {
+                value: Some(value),
+                generation,
+            }) if *generation == index.generation => Ok(value),
+            _ => Err(format!(
+                "stale id {}: it has been removed from its engine",
+                index.index
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_index_test() {
+        let slab = ConcurrentSlab::<u32>::default();
+        let index = slab.insert(1);
+        slab.clear();
+        slab.insert(2);
+        let err = slab.get(index).unwrap_err();
+        assert!(err.starts_with("stale id 0"), "{}", err);
+        assert!(slab.set(index, Arc::new(3)).is_err());
+        assert!(slab.replace(index, &2, Arc::new(3)).is_err());
+        assert_eq!(*slab.get(slab.insert(4)).unwrap(), 4);
+    }
+
+    #[test]
+    fn removed_index_test() {
+        let slab = ConcurrentSlab::<u32>::default();
+        let index = slab.insert(1);
+        let kept = slab.insert(2);
+        slab.remove(index).unwrap();
+        assert_eq!(slab.len(), 1);
+
+        // the slot is reused, but the removed index does not see the new value
+        let reused = slab.insert(3);
+        assert_eq!(*reused, *index);
+        assert_eq!(slab.len(), 2);
+        let err = slab.get(index).unwrap_err();
+        assert!(err.contains("has been removed"), "{}", err);
+        assert!(slab.remove(index).is_err());
+        assert_eq!(*slab.get(reused).unwrap(), 3);
+        assert_eq!(*slab.get(kept).unwrap(), 2);
+
+        // an index removed before clearing stays stale after the slot is
+        // filled again
+        slab.remove(reused).unwrap();
+        slab.clear();
+        slab.insert(4);
+        slab.insert(5);
+        assert!(slab.get(reused).is_err());
+        assert!(slab.get(index).is_err());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn foreign_index_test() {
+        let slab = ConcurrentSlab::<u32>::default();
+        let other = ConcurrentSlab::<u32>::default();
+        slab.insert(1);
+        let index = other.insert(2);
+        let err = slab.get(index).unwrap_err();
+        assert!(err.contains("used with engine"), "{}", err);
+        assert!(slab.validate(index).is_err());
     }
 }
@@ -1,217 +1,758 @@
 use std::collections::HashMap;
-use std::sync::RwLock;
-
-use lazy_static::lazy_static;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 
 use crate::{
+    collection_context::collection_index::CollectionIndex,
     concurrent_slab::ConcurrentSlab,
     language::ty::typed_declaration::{
         TyFunctionDeclaration, TyStructDeclaration, TyTraitDeclaration, TyTraitFn, TyTraitImpl,
     },
+    session::Session,
+    type_system::{
+        type_engine::{are_equivalent_types, concrete_type_arguments},
+        type_id::TypeId,
+        type_parameter::TypeParameter,
+    },
 };
 
-use super::{declaration_id::DeclarationId, declaration_wrapper::DeclarationWrapper};
-
-lazy_static! {
-    static ref DECLARATION_ENGINE: DeclarationEngine = DeclarationEngine::default();
-}
+use super::{
+    declaration_id::{DeclId, DeclarationId},
+    declaration_wrapper::{Declaration, DeclarationWrapper},
+    instantiation::Instantiation,
+};
 
-/// Used inside of type inference to store declarations.
+/// Used inside of type inference to store declarations, owned by a [Session].
 #[derive(Default)]
-struct DeclarationEngine {
+pub(crate) struct DeclarationEngine {
     slab: ConcurrentSlab<DeclarationWrapper>,
-    // *declaration_id -> vec of monomorphized copies
+    // declaration_id -> monomorphized copies, in the order they were added
     // where the declaration_id is the original declaration
-    monomorphized_copies: RwLock<HashMap<usize, Vec<DeclarationId>>>,
+    monomorphized_copies: RwLock<HashMap<DeclarationId, Vec<Instantiation>>>,
+    // (declaration_id, type arguments) -> monomorphized copy
+    // for the copies whose type arguments were concrete when they were added
+    instantiations: RwLock<HashMap<(DeclarationId, Vec<TypeId>), DeclarationId>>,
+    // declaration_id -> the declaration that it is declared as part of
+    parents: RwLock<HashMap<DeclarationId, DeclarationId>>,
+    // (trait, trait impl) for every trait impl of the application being
+    // compiled, in the order they are declared
+    trait_impls: RwLock<Vec<(DeclId<TyTraitDeclaration>, DeclId<TyTraitImpl>)>>,
+    // struct name -> file -> the number of structs of that name, including
+    // copies, that are declared in that file
+    struct_files: RwLock<HashMap<String, HashMap<String, usize>>>,
 }
 
 impl DeclarationEngine {
-    fn clear(&self) {
+    /// Removes every declaration, which makes every [DeclarationId] created so
+    /// far stale.
+    pub(crate) fn clear(&self) {
         self.slab.clear();
         let mut monomorphized_copies = self.monomorphized_copies.write().unwrap();
         monomorphized_copies.clear();
+        let mut instantiations = self.instantiations.write().unwrap();
+        instantiations.clear();
+        let mut parents = self.parents.write().unwrap();
+        parents.clear();
+        let mut trait_impls = self.trait_impls.write().unwrap();
+        trait_impls.clear();
+        let mut struct_files = self.struct_files.write().unwrap();
+        struct_files.clear();
     }
 
-    fn insert(&self, value: DeclarationWrapper) -> DeclarationId {
-        DeclarationId::new(self.slab.insert(value))
+    fn insert<T>(&self, value: T) -> DeclId<T>
+    where
+        T: Declaration,
+    {
+        let wrapper = value.into_wrapper();
+        let children = wrapper.children();
+        self.add_struct_file(&wrapper);
+        let index = DeclarationId::new(self.slab.insert(wrapper));
+        self.adopt(index, children);
+        DeclId::new(index)
     }
 
-    fn look_up_decl_id(&self, index: DeclarationId) -> DeclarationWrapper {
-        self.slab.get(*index)
+    /// Records the file that `wrapper` is declared in, if it is a struct, so
+    /// that [DeclarationEngine::struct_path] knows which names are ambiguous.
+    fn add_struct_file(&self, wrapper: &DeclarationWrapper) {
+        if let DeclarationWrapper::Struct(r#struct) = wrapper {
+            let mut struct_files = self.struct_files.write().unwrap();
+            *struct_files
+                .entry(r#struct.name.clone())
+                .or_default()
+                .entry(r#struct.file.clone())
+                .or_default() += 1;
+        }
     }
 
-    fn add_monomorphized_copy(&self, original_id: DeclarationId, new_id: DeclarationId) {
-        let mut monomorphized_copies = self.monomorphized_copies.write().unwrap();
-        match monomorphized_copies.get_mut(&*original_id) {
-            Some(prev) => {
-                prev.push(new_id);
+    /// Undoes [DeclarationEngine::add_struct_file] for `wrapper`.
+    fn remove_struct_file(&self, wrapper: &DeclarationWrapper) {
+        if let DeclarationWrapper::Struct(r#struct) = wrapper {
+            let mut struct_files = self.struct_files.write().unwrap();
+            if let Some(files) = struct_files.get_mut(&r#struct.name) {
+                if let Some(count) = files.get_mut(&r#struct.file) {
+                    *count -= 1;
+                    if *count == 0 {
+                        files.remove(&r#struct.file);
+                    }
+                }
+                if files.is_empty() {
+                    struct_files.remove(&r#struct.name);
+                }
             }
-            None => {
-                monomorphized_copies.insert(*original_id, vec![new_id]);
+        }
+    }
+
+    /// Records `parent` as the parent of every declaration in `children`.
+    fn adopt(&self, parent: DeclarationId, children: Vec<DeclarationId>) {
+        let mut parents = self.parents.write().unwrap();
+        for child in children.into_iter() {
+            parents.insert(child, parent);
+        }
+    }
+
+    /// Forgets `parent` as the parent of every declaration in `children`.
+    fn disown(&self, parent: DeclarationId, children: Vec<DeclarationId>) {
+        let mut parents = self.parents.write().unwrap();
+        for child in children.into_iter() {
+            if parents.get(&child) == Some(&parent) {
+                parents.remove(&child);
             }
         }
     }
 
-    fn get_monomorphized_copies(&self, original_id: DeclarationId) -> Vec<DeclarationWrapper> {
-        let monomorphized_copies = self.monomorphized_copies.write().unwrap();
-        match monomorphized_copies.get(&*original_id).cloned() {
-            Some(copies) => copies
-                .into_iter()
-                .map(|copy| self.slab.get(*copy))
-                .collect(),
-            None => vec![],
+    fn get<T>(&self, index: DeclId<T>) -> Result<Arc<T>, String>
+    where
+        T: Declaration,
+    {
+        T::from_wrapper(&*self.slab.get(index.untyped().slab_index())?)
+    }
+
+    /// Stores `value` as the declaration `index`, which errors if `index` is
+    /// stale or refers to a different kind of declaration. Declarations that
+    /// were gotten before keep their old value.
+    fn replace<T>(&self, index: DeclId<T>, value: T) -> Result<(), String>
+    where
+        T: Declaration,
+    {
+        let index = index.untyped();
+        let previous = self.slab.get(index.slab_index())?;
+        T::from_wrapper(&previous)?;
+        let previous_children = previous.children();
+        let wrapper = value.into_wrapper();
+        let children = wrapper.children();
+        self.remove_struct_file(&previous);
+        self.add_struct_file(&wrapper);
+        self.slab.set(index.slab_index(), Arc::new(wrapper))?;
+        self.disown(index, previous_children);
+        self.adopt(index, children);
+        Ok(())
+    }
+
+    /// Removes the declaration `index` and its monomorphized copies, which
+    /// makes their ids stale.
+    fn remove(&self, index: DeclarationId) -> Result<(), String> {
+        let copies = self
+            .monomorphized_copies
+            .write()
+            .unwrap()
+            .remove(&index)
+            .unwrap_or_default();
+        self.instantiations
+            .write()
+            .unwrap()
+            .retain(|(original_id, _), _| *original_id != index);
+        for id in std::iter::once(index).chain(copies.iter().map(|copy| copy.copy_id)) {
+            let wrapper = self.slab.get(id.slab_index())?;
+            self.remove_struct_file(&wrapper);
+            self.disown(id, wrapper.children());
+            self.parents.write().unwrap().remove(&id);
+            self.slab.remove(id.slab_index())?;
         }
+        Ok(())
     }
 
-    fn insert_function(&self, function: TyFunctionDeclaration) -> DeclarationId {
-        self.insert(DeclarationWrapper::Function(function))
+    /// The number of declarations, including monomorphized copies.
+    #[allow(dead_code)]
+    pub(crate) fn len(&self) -> usize {
+        self.slab.len()
     }
 
-    fn get_function(&self, index: DeclarationId) -> Result<TyFunctionDeclaration, String> {
-        self.slab.get(*index).expect_function()
+    fn look_up_decl_id(&self, index: DeclarationId) -> Result<Arc<DeclarationWrapper>, String> {
+        self.slab.get(index.slab_index())
     }
 
-    fn add_monomorphized_function_copy(
-        &self,
-        original_id: DeclarationId,
-        new_copy: TyFunctionDeclaration,
-    ) {
-        let new_id = self.insert(DeclarationWrapper::Function(new_copy));
-        self.add_monomorphized_copy(original_id, new_id)
+    fn expect_decl_id<T>(&self, index: DeclarationId) -> Result<DeclId<T>, String>
+    where
+        T: Declaration,
+    {
+        T::from_wrapper(&*self.slab.get(index.slab_index())?)?;
+        Ok(DeclId::new(index))
     }
 
-    fn get_monomorphized_function_copies(
+    /// Adds `new_copy` as a monomorphized copy of `original_id` that was
+    /// requested by `requested_by`, and returns the id of the copy. If
+    /// `concrete_arguments` is given and a copy has already been added for the
+    /// same type arguments, the id of that copy is returned instead, and
+    /// `requested_by` is recorded as requesting it too.
+    fn add_monomorphized_copy<T>(
         &self,
-        original_id: DeclarationId,
-    ) -> Result<Vec<TyFunctionDeclaration>, String> {
-        self.get_monomorphized_copies(original_id)
-            .into_iter()
-            .map(|x| x.expect_function())
-            .collect::<Result<_, _>>()
+        original_id: DeclId<T>,
+        concrete_arguments: Option<Vec<TypeId>>,
+        type_arguments: Vec<TypeId>,
+        requested_by: CollectionIndex,
+        new_copy: T,
+    ) -> DeclId<T>
+    where
+        T: Declaration,
+    {
+        let mut instantiations = self.instantiations.write().unwrap();
+        let key = concrete_arguments
+            .map(|concrete_arguments| (original_id.untyped(), concrete_arguments));
+        if let Some(copy_id) = key.as_ref().and_then(|key| instantiations.get(key)) {
+            self.add_requester(original_id.untyped(), *copy_id, requested_by);
+            return DeclId::new(*copy_id);
+        }
+        let copy_id = self.insert(new_copy);
+        // a copy is declared as part of the same declaration as its original
+        if let Some(parent) = self.parent_of(original_id.untyped()) {
+            self.adopt(parent, vec![copy_id.untyped()]);
+        }
+        if let Some(key) = key {
+            instantiations.insert(key, copy_id.untyped());
+        }
+        let mut monomorphized_copies = self.monomorphized_copies.write().unwrap();
+        monomorphized_copies
+            .entry(original_id.untyped())
+            .or_default()
+            .push(Instantiation {
+                original_id: original_id.untyped(),
+                copy_id: copy_id.untyped(),
+                type_arguments,
+                requested_by: vec![requested_by],
+            });
+        copy_id
     }
 
-    fn insert_trait(&self, r#trait: TyTraitDeclaration) -> DeclarationId {
-        self.insert(DeclarationWrapper::Trait(r#trait))
+    /// The copy of `original_id` that was added for the concrete type arguments
+    /// `concrete_arguments`, if any, which `requested_by` is recorded as
+    /// requesting too.
+    fn find_monomorphized_copy<T>(
+        &self,
+        original_id: DeclId<T>,
+        concrete_arguments: &[TypeId],
+        requested_by: CollectionIndex,
+    ) -> Option<DeclId<T>>
+    where
+        T: Declaration,
+    {
+        let key = (original_id.untyped(), concrete_arguments.to_vec());
+        let copy_id = *self.instantiations.read().unwrap().get(&key)?;
+        self.add_requester(original_id.untyped(), copy_id, requested_by);
+        Some(DeclId::new(copy_id))
     }
 
-    fn get_trait(&self, index: DeclarationId) -> Result<TyTraitDeclaration, String> {
-        self.slab.get(*index).expect_trait()
+    /// Records `requested_by` as requesting the copy `copy_id` of `original_id`.
+    fn add_requester(
+        &self,
+        original_id: DeclarationId,
+        copy_id: DeclarationId,
+        requested_by: CollectionIndex,
+    ) {
+        let mut monomorphized_copies = self.monomorphized_copies.write().unwrap();
+        let instantiation = monomorphized_copies
+            .get_mut(&original_id)
+            .and_then(|copies| copies.iter_mut().find(|copy| copy.copy_id == copy_id))
+            .unwrap();
+        if !instantiation.requested_by.contains(&requested_by) {
+            instantiation.requested_by.push(requested_by);
+        }
     }
 
-    fn insert_trait_fn(&self, trait_fn: TyTraitFn) -> DeclarationId {
-        self.insert(DeclarationWrapper::TraitFn(trait_fn))
+    fn instantiations_of(&self, original_id: DeclarationId) -> Vec<Instantiation> {
+        let monomorphized_copies = self.monomorphized_copies.read().unwrap();
+        monomorphized_copies
+            .get(&original_id)
+            .cloned()
+            .unwrap_or_default()
     }
 
-    fn get_trait_fn(&self, index: DeclarationId) -> Result<TyTraitFn, String> {
-        self.slab.get(*index).expect_trait_fn()
+    fn parent_of(&self, index: DeclarationId) -> Option<DeclarationId> {
+        let parents = self.parents.read().unwrap();
+        parents.get(&index).copied()
     }
 
-    fn insert_trait_impl(&self, trait_impl: TyTraitImpl) -> DeclarationId {
-        self.insert(DeclarationWrapper::TraitImpl(trait_impl))
+    #[allow(dead_code)]
+    fn children_of(&self, index: DeclarationId) -> Result<Vec<DeclarationId>, String> {
+        Ok(self.look_up_decl_id(index)?.children())
     }
 
-    fn get_trait_impl(&self, index: DeclarationId) -> Result<TyTraitImpl, String> {
-        self.slab.get(*index).expect_trait_impl()
+    fn set_trait_impls(&self, impls: Vec<(DeclId<TyTraitDeclaration>, DeclId<TyTraitImpl>)>) {
+        let mut trait_impls = self.trait_impls.write().unwrap();
+        *trait_impls = impls;
     }
 
-    fn insert_struct(&self, r#struct: TyStructDeclaration) -> DeclarationId {
-        self.insert(DeclarationWrapper::Struct(r#struct))
+    fn impls_of_trait(&self, trait_id: DeclId<TyTraitDeclaration>) -> Vec<DeclId<TyTraitImpl>> {
+        let trait_impls = self.trait_impls.read().unwrap();
+        trait_impls
+            .iter()
+            .filter(|(impl_trait_id, _)| *impl_trait_id == trait_id)
+            .map(|(_, impl_id)| *impl_id)
+            .collect()
     }
 
-    fn get_struct(&self, index: DeclarationId) -> Result<TyStructDeclaration, String> {
-        self.slab.get(*index).expect_struct()
+    fn all_trait_impls(&self) -> Vec<DeclId<TyTraitImpl>> {
+        let trait_impls = self.trait_impls.read().unwrap();
+        trait_impls.iter().map(|(_, impl_id)| *impl_id).collect()
     }
 
-    fn add_monomorphized_struct_copy(
-        &self,
-        original_id: DeclarationId,
-        new_copy: TyStructDeclaration,
-    ) {
-        let new_id = self.insert(DeclarationWrapper::Struct(new_copy));
-        self.add_monomorphized_copy(original_id, new_id)
+    fn struct_path(&self, index: DeclId<TyStructDeclaration>) -> Result<String, String> {
+        let r#struct = self.get(index)?;
+        // only qualify the name if another struct of the same name is declared
+        // in a different file
+        let is_ambiguous = self
+            .struct_files
+            .read()
+            .unwrap()
+            .get(&r#struct.name)
+            .is_some_and(|files| files.len() > 1);
+        if is_ambiguous {
+            Ok(format!("{}::{}", file_stem(&r#struct.file), r#struct.name))
+        } else {
+            Ok(r#struct.name.clone())
+        }
     }
+}
 
-    fn get_monomorphized_struct_copies(
-        &self,
-        original_id: DeclarationId,
-    ) -> Result<Vec<TyStructDeclaration>, String> {
-        self.get_monomorphized_copies(original_id)
-            .into_iter()
-            .map(|x| x.expect_struct())
-            .collect::<Result<_, _>>()
-    }
+pub(crate) fn de_look_up_decl_id(
+    session: &Session,
+    index: DeclarationId,
+) -> Result<Arc<DeclarationWrapper>, String> {
+    session.declaration_engine.look_up_decl_id(index)
+}
+
+/// Checks that `index` refers to a declaration of type `T`, such as a
+/// [TyFunctionDeclaration], and returns it as a [DeclId] of that type.
+pub(crate) fn de_expect_decl_id<T>(
+    session: &Session,
+    index: DeclarationId,
+) -> Result<DeclId<T>, String>
+where
+    T: Declaration,
+{
+    session.declaration_engine.expect_decl_id(index)
 }
 
-pub(crate) fn de_clear() {
-    DECLARATION_ENGINE.clear()
+/// Removes the declaration `index` and its monomorphized copies, such as when
+/// the node that declared it is gone from the
+/// [CollectionContext](crate::collection_context::collection_context::CollectionContext).
+pub(crate) fn de_remove(session: &Session, index: DeclarationId) -> Result<(), String> {
+    session.declaration_engine.remove(index)
 }
 
-pub(crate) fn de_look_up_decl_id(index: DeclarationId) -> DeclarationWrapper {
-    DECLARATION_ENGINE.look_up_decl_id(index)
+/// The declaration that `index` is declared as part of, such as the trait of a
+/// trait fn or the trait impl of a method. The monomorphized copies of a method
+/// have the same parent as the method.
+pub(crate) fn de_parent_of(session: &Session, index: DeclarationId) -> Option<DeclarationId> {
+    session.declaration_engine.parent_of(index)
 }
 
-pub(crate) fn de_insert_function(function: TyFunctionDeclaration) -> DeclarationId {
-    DECLARATION_ENGINE.insert_function(function)
+pub(crate) fn de_insert_function(
+    session: &Session,
+    function: TyFunctionDeclaration,
+) -> DeclId<TyFunctionDeclaration> {
+    session.declaration_engine.insert(function)
 }
 
-pub(crate) fn de_get_function(index: DeclarationId) -> Result<TyFunctionDeclaration, String> {
-    DECLARATION_ENGINE.get_function(index)
+pub(crate) fn de_get_function(
+    session: &Session,
+    index: DeclId<TyFunctionDeclaration>,
+) -> Result<Arc<TyFunctionDeclaration>, String> {
+    session.declaration_engine.get(index)
 }
 
+/// Stores `function` as the function `index`, such as after type inference has
+/// refined it.
+pub(crate) fn de_replace_function(
+    session: &Session,
+    index: DeclId<TyFunctionDeclaration>,
+    function: TyFunctionDeclaration,
+) -> Result<(), String> {
+    session.declaration_engine.replace(index, function)
+}
+
+/// Adds `new_copy` as a monomorphized copy of the function `original_id` that
+/// was requested by the node `requested_by`, and returns the id of the copy to
+/// use, which is an earlier copy if one was already added for the same concrete
+/// type arguments.
 pub(crate) fn de_add_monomorphized_function_copy(
-    original_id: DeclarationId,
+    session: &Session,
+    original_id: DeclId<TyFunctionDeclaration>,
+    requested_by: CollectionIndex,
     new_copy: TyFunctionDeclaration,
-) {
-    DECLARATION_ENGINE.add_monomorphized_function_copy(original_id, new_copy);
+) -> Result<DeclId<TyFunctionDeclaration>, String> {
+    let concrete_arguments = concrete_type_arguments(session, &new_copy.type_parameters)?;
+    Ok(session.declaration_engine.add_monomorphized_copy(
+        original_id,
+        concrete_arguments,
+        type_argument_ids(&new_copy.type_parameters),
+        requested_by,
+        new_copy,
+    ))
 }
 
-pub(crate) fn de_get_monomorphized_function_copies(
-    original_id: DeclarationId,
-) -> Result<Vec<TyFunctionDeclaration>, String> {
-    DECLARATION_ENGINE.get_monomorphized_function_copies(original_id)
+pub(crate) fn de_insert_trait(
+    session: &Session,
+    r#trait: TyTraitDeclaration,
+) -> DeclId<TyTraitDeclaration> {
+    session.declaration_engine.insert(r#trait)
 }
 
-pub(crate) fn de_insert_trait(r#trait: TyTraitDeclaration) -> DeclarationId {
-    DECLARATION_ENGINE.insert_trait(r#trait)
+pub(crate) fn de_get_trait(
+    session: &Session,
+    index: DeclId<TyTraitDeclaration>,
+) -> Result<Arc<TyTraitDeclaration>, String> {
+    session.declaration_engine.get(index)
 }
 
-pub(crate) fn de_get_trait(index: DeclarationId) -> Result<TyTraitDeclaration, String> {
-    DECLARATION_ENGINE.get_trait(index)
+pub(crate) fn de_insert_trait_fn(session: &Session, trait_fn: TyTraitFn) -> DeclId<TyTraitFn> {
+    session.declaration_engine.insert(trait_fn)
 }
 
-pub(crate) fn de_insert_trait_fn(trait_fn: TyTraitFn) -> DeclarationId {
-    DECLARATION_ENGINE.insert_trait_fn(trait_fn)
+pub(crate) fn de_get_trait_fn(
+    session: &Session,
+    index: DeclId<TyTraitFn>,
+) -> Result<Arc<TyTraitFn>, String> {
+    session.declaration_engine.get(index)
 }
 
-pub(crate) fn de_get_trait_fn(index: DeclarationId) -> Result<TyTraitFn, String> {
-    DECLARATION_ENGINE.get_trait_fn(index)
+pub(crate) fn de_replace_trait_fn(
+    session: &Session,
+    index: DeclId<TyTraitFn>,
+    trait_fn: TyTraitFn,
+) -> Result<(), String> {
+    session.declaration_engine.replace(index, trait_fn)
 }
 
-pub(crate) fn de_insert_trait_impl(trait_impl: TyTraitImpl) -> DeclarationId {
-    DECLARATION_ENGINE.insert_trait_impl(trait_impl)
+pub(crate) fn de_insert_trait_impl(
+    session: &Session,
+    trait_impl: TyTraitImpl,
+) -> DeclId<TyTraitImpl> {
+    session.declaration_engine.insert(trait_impl)
 }
 
-pub(crate) fn de_get_trait_impl(index: DeclarationId) -> Result<TyTraitImpl, String> {
-    DECLARATION_ENGINE.get_trait_impl(index)
+pub(crate) fn de_get_trait_impl(
+    session: &Session,
+    index: DeclId<TyTraitImpl>,
+) -> Result<Arc<TyTraitImpl>, String> {
+    session.declaration_engine.get(index)
 }
 
-pub(crate) fn de_insert_struct(r#struct: TyStructDeclaration) -> DeclarationId {
-    DECLARATION_ENGINE.insert_struct(r#struct)
+pub(crate) fn de_replace_trait_impl(
+    session: &Session,
+    index: DeclId<TyTraitImpl>,
+    trait_impl: TyTraitImpl,
+) -> Result<(), String> {
+    session.declaration_engine.replace(index, trait_impl)
+}
+
+/// Records the trait impls of the application being compiled, each with the
+/// trait that it implements, in the order they are declared. These replace the
+/// trait impls of the previously compiled application.
+pub(crate) fn de_set_trait_impls(
+    session: &Session,
+    impls: Vec<(DeclId<TyTraitDeclaration>, DeclId<TyTraitImpl>)>,
+) {
+    session.declaration_engine.set_trait_impls(impls)
 }
 
-pub(crate) fn de_get_struct(index: DeclarationId) -> Result<TyStructDeclaration, String> {
-    DECLARATION_ENGINE.get_struct(index)
+/// The trait impls of the trait `trait_id`, in the order they are declared.
+pub(crate) fn de_impls_of_trait(
+    session: &Session,
+    trait_id: DeclId<TyTraitDeclaration>,
+) -> Vec<DeclId<TyTraitImpl>> {
+    session.declaration_engine.impls_of_trait(trait_id)
 }
 
+/// The trait impls that implement a trait for a type equivalent to `type_id`, in
+/// the order they are declared. A trait impl for a struct is only found once
+/// type collection has resolved the struct that it names.
+pub(crate) fn de_impls_for_type(
+    session: &Session,
+    type_id: TypeId,
+) -> Result<Vec<DeclId<TyTraitImpl>>, String> {
+    let mut impls = vec![];
+    for impl_id in session.declaration_engine.all_trait_impls() {
+        let trait_impl = de_get_trait_impl(session, impl_id)?;
+        if are_equivalent_types(session, trait_impl.type_implementing_for, type_id)? {
+            impls.push(impl_id);
+        }
+    }
+    Ok(impls)
+}
+
+pub(crate) fn de_insert_struct(
+    session: &Session,
+    r#struct: TyStructDeclaration,
+) -> DeclId<TyStructDeclaration> {
+    session.declaration_engine.insert(r#struct)
+}
+
+pub(crate) fn de_get_struct(
+    session: &Session,
+    index: DeclId<TyStructDeclaration>,
+) -> Result<Arc<TyStructDeclaration>, String> {
+    session.declaration_engine.get(index)
+}
+
+pub(crate) fn de_replace_struct(
+    session: &Session,
+    index: DeclId<TyStructDeclaration>,
+    r#struct: TyStructDeclaration,
+) -> Result<(), String> {
+    session.declaration_engine.replace(index, r#struct)
+}
+
+/// The name of the struct `index` for use in messages, prefixed with the file
+/// that it is declared in if that is needed to tell it apart from other structs.
+pub(crate) fn de_struct_path(
+    session: &Session,
+    index: DeclId<TyStructDeclaration>,
+) -> Result<String, String> {
+    session.declaration_engine.struct_path(index)
+}
+
+/// Adds `new_copy` as a monomorphized copy of the struct `original_id` that
+/// was requested by the node `requested_by`, and returns the id of the copy to
+/// use, which is an earlier copy if one was already added for the same concrete
+/// type arguments.
 pub(crate) fn de_add_monomorphized_struct_copy(
-    original_id: DeclarationId,
+    session: &Session,
+    original_id: DeclId<TyStructDeclaration>,
+    requested_by: CollectionIndex,
     new_copy: TyStructDeclaration,
-) {
-    DECLARATION_ENGINE.add_monomorphized_struct_copy(original_id, new_copy);
+) -> Result<DeclId<TyStructDeclaration>, String> {
+    let concrete_arguments = concrete_type_arguments(session, &new_copy.type_parameters)?;
+    Ok(session.declaration_engine.add_monomorphized_copy(
+        original_id,
+        concrete_arguments,
+        type_argument_ids(&new_copy.type_parameters),
+        requested_by,
+        new_copy,
+    ))
+}
+
+/// The copy of the function `original_id` that was added for the concrete type
+/// arguments `concrete_arguments`, if any, which the node `requested_by` is
+/// recorded as requesting too.
+pub(crate) fn de_find_monomorphized_function_copy(
+    session: &Session,
+    original_id: DeclId<TyFunctionDeclaration>,
+    concrete_arguments: &[TypeId],
+    requested_by: CollectionIndex,
+) -> Option<DeclId<TyFunctionDeclaration>> {
+    session.declaration_engine.find_monomorphized_copy(
+        original_id,
+        concrete_arguments,
+        requested_by,
+    )
 }
 
-pub(crate) fn de_get_monomorphized_struct_copies(
-    original_id: DeclarationId,
-) -> Result<Vec<TyStructDeclaration>, String> {
-    DECLARATION_ENGINE.get_monomorphized_struct_copies(original_id)
+/// The monomorphized copies of the declaration `original_id`, with the type
+/// arguments and the requesting nodes of each, in the order they were added.
+pub(crate) fn de_get_instantiations<T>(
+    session: &Session,
+    original_id: DeclId<T>,
+) -> Vec<Instantiation>
+where
+    T: Declaration,
+{
+    session
+        .declaration_engine
+        .instantiations_of(original_id.untyped())
+}
+
+fn type_argument_ids(type_parameters: &[TypeParameter]) -> Vec<TypeId> {
+    type_parameters
+        .iter()
+        .map(|type_parameter| type_parameter.type_id)
+        .collect()
+}
+
+/// The name of `file` without its extension, e.g. `bob` for `bob.sw`.
+fn file_stem(file: &str) -> &str {
+    Path::new(file)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
+        language::{
+            parsed::{
+                constructors::*, declaration::constructors::*, expression::constructors::*,
+                Application, File,
+            },
+            ty::{typed_declaration::TyDeclaration, TyApplication, TyNode},
+        },
+        query_engine::{query_cache::QueryCache, query_engine::QueryEngine},
+        semantic_analysis::graph_collection::collect_graph,
+        type_system::{
+            type_engine::insert_type,
+            type_info::{constructors::*, TypeInfo},
+        },
+    };
+
+    /// The trait `Math`, implemented for the types `types` in that order, after
+    /// graph collection.
+    fn math_impls(
+        session: &Session,
+        cc: &mut CollectionContext,
+        types: &[TypeInfo],
+    ) -> CCIdx<TyApplication> {
+        let mut nodes = vec![trait_(
+            "Math",
+            &[trait_fn("add", &[func_param("a", t_u8())], t_u8())],
+        )];
+        for type_info in types.iter() {
+            let add_fn = func_decl_raw(
+                "add",
+                &[],
+                &[func_param("a", t_u8())],
+                &[return_(var("a"))],
+                t_u8(),
+            );
+            nodes.push(trait_impl("Math", type_info.clone(), &[], &[add_fn]));
+        }
+        let application = Application {
+            files: vec![File {
+                name: "bob.sw".to_string(),
+                nodes,
+            }],
+        };
+        collect_graph(session, cc, application).unwrap()
+    }
+
+    fn declarations(application: &CCIdx<TyApplication>) -> Vec<TyDeclaration> {
+        application.inner_ref().files[0]
+            .inner_ref()
+            .nodes
+            .iter()
+            .filter_map(|node| match node.inner_ref() {
+                TyNode::Declaration(decl) => Some(decl.inner_ref().clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn trait_of(decl: &TyDeclaration) -> DeclId<TyTraitDeclaration> {
+        match decl {
+            TyDeclaration::Trait(decl_id) => *decl_id.inner_ref(),
+            _ => panic!("expected a trait"),
+        }
+    }
+
+    fn impl_of(decl: &TyDeclaration) -> DeclId<TyTraitImpl> {
+        match decl {
+            TyDeclaration::TraitImpl(decl_id) => *decl_id.inner_ref(),
+            _ => panic!("expected a trait impl"),
+        }
+    }
+
+    #[test]
+    fn parents_and_children_test() {
+        let session = Session::new();
+        let mut cc = CollectionContext::default();
+        let application = math_impls(&session, &mut cc, &[t_u8()]);
+        let decls = declarations(&application);
+        let trait_id = trait_of(&decls[0]);
+        let impl_id = impl_of(&decls[1]);
+        let trait_fn_id =
+            *de_get_trait(&session, trait_id).unwrap().interface_surface[0].inner_ref();
+        let trait_impl = de_get_trait_impl(&session, impl_id).unwrap();
+        let method_id = *trait_impl.methods[0].inner_ref();
+
+        assert_eq!(
+            de_parent_of(&session, trait_fn_id.untyped()),
+            Some(trait_id.untyped())
+        );
+        assert_eq!(
+            de_parent_of(&session, method_id.untyped()),
+            Some(impl_id.untyped())
+        );
+        assert_eq!(de_parent_of(&session, impl_id.untyped()), None);
+        assert_eq!(
+            session
+                .declaration_engine
+                .children_of(impl_id.untyped())
+                .unwrap(),
+            vec![method_id.untyped()]
+        );
+
+        // a method that a new version of the trait impl no longer has is no
+        // longer a child of it
+        let mut without_methods = (*trait_impl).clone();
+        without_methods.methods.clear();
+        de_replace_trait_impl(&session, impl_id, without_methods).unwrap();
+        assert_eq!(de_parent_of(&session, method_id.untyped()), None);
+        assert_eq!(
+            session
+                .declaration_engine
+                .children_of(impl_id.untyped())
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn trait_impls_test() {
+        let session = Session::new();
+        let mut cc = CollectionContext::default();
+        let application = math_impls(&session, &mut cc, &[t_u64(), t_u8(), t_u64()]);
+        let decls = declarations(&application);
+        let trait_id = trait_of(&decls[0]);
+        let impl_ids = decls[1..].iter().map(impl_of).collect::<Vec<_>>();
+        let u64_id = insert_type(&session, t_u64());
+        let u8_id = insert_type(&session, t_u8());
+
+        session.enter(|| {
+            let mut cache = QueryCache::default();
+            let mut qe = QueryEngine::new(&session, &cc, &application, &mut cache);
+
+            // the impls are known as soon as the application is, and in the
+            // order they are declared
+            assert_eq!(de_impls_of_trait(&session, trait_id), impl_ids);
+
+            qe.type_check_application().unwrap();
+            assert_eq!(
+                de_impls_for_type(&session, u64_id),
+                Ok(vec![impl_ids[0], impl_ids[2]])
+            );
+            assert_eq!(de_impls_for_type(&session, u8_id), Ok(vec![impl_ids[1]]));
+        });
+
+        // the impls of the application compiled next in the same session
+        // replace those of the previous one
+        let mut cc = CollectionContext::default();
+        let next_application = math_impls(&session, &mut cc, &[t_u8()]);
+        let next_decls = declarations(&next_application);
+        session.enter(|| {
+            let mut cache = QueryCache::default();
+            QueryEngine::new(&session, &cc, &next_application, &mut cache)
+                .type_check_application()
+                .unwrap();
+        });
+        assert_eq!(de_impls_of_trait(&session, trait_id), vec![]);
+        assert_eq!(
+            de_impls_of_trait(&session, trait_of(&next_decls[0])),
+            vec![impl_of(&next_decls[1])]
+        );
+        assert_eq!(
+            de_impls_for_type(&session, u8_id),
+            Ok(vec![impl_of(&next_decls[1])])
+        );
+    }
 }
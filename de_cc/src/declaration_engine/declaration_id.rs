@@ -1,31 +1,36 @@
-use std::fmt;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
 
-use crate::{type_system::type_mapping::TypeMapping, types::copy_types::CopyTypes};
+use crate::{
+    concurrent_slab::SlabIndex,
+    session::{with_active_session, Session},
+    type_system::type_mapping::TypeMapping,
+    types::copy_types::CopyTypes,
+};
 
 use super::declaration_engine::de_look_up_decl_id;
 
 /// An ID used to refer to an item in the [DeclarationEngine](super::declaration_engine::DeclarationEngine)
-#[derive(Debug, Eq, Copy)]
-pub struct DeclarationId(usize);
-
-impl Clone for DeclarationId {
-    fn clone(&self) -> Self {
-        Self(self.0)
-    }
-}
-
-// NOTE: Hash and PartialEq must uphold the invariant:
-// k1 == k2 -> hash(k1) == hash(k2)
-// https://doc.rust-lang.org/std/collections/struct.HashMap.html
-impl PartialEq for DeclarationId {
-    fn eq(&self, other: &Self) -> bool {
-        de_look_up_decl_id(*self) == de_look_up_decl_id(*other)
-    }
-}
+/// of a [Session], which can be any kind of declaration. Where the kind is
+/// known, a [DeclId] is used instead.
+///
+/// IDs are equal, and hash the same, only if they are the same ID, which does
+/// not need to look at the declaration engine. To compare the declarations that
+/// IDs refer to, compare the [DeclarationWrapper](super::declaration_wrapper::DeclarationWrapper)s
+/// returned by [de_look_up_decl_id].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct DeclarationId(SlabIndex);
 
 impl fmt::Display for DeclarationId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", de_look_up_decl_id(*self))
+        match with_active_session(|session| de_look_up_decl_id(session, *self)) {
+            Some(Ok(decl)) => write!(f, "{}", decl),
+            Some(Err(err)) => write!(f, "<{}>", err),
+            None => write!(f, "<decl #{}>", **self),
+        }
     }
 }
 
@@ -39,18 +44,95 @@ impl std::ops::Deref for DeclarationId {
 #[allow(clippy::from_over_into)]
 impl Into<usize> for DeclarationId {
     fn into(self) -> usize {
-        self.0
+        *self.0
     }
 }
 
 impl CopyTypes for DeclarationId {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        de_look_up_decl_id(*self).copy_types(type_mapping)
+    fn copy_types(&mut self, _session: &Session, _type_mapping: &TypeMapping) {
+        // the declaration is shared through the declaration engine, and is
+        // monomorphized by copying it there rather than through its id
     }
 }
 
 impl DeclarationId {
-    pub(super) fn new(index: usize) -> DeclarationId {
+    pub(super) fn new(index: SlabIndex) -> DeclarationId {
         DeclarationId(index)
     }
+
+    pub(super) fn slab_index(&self) -> SlabIndex {
+        self.0
+    }
+}
+
+/// A [DeclarationId] that is known to refer to a declaration of type `T`, such
+/// as a `DeclId<TyFunctionDeclaration>`.
+///
+/// These are only handed out by the [DeclarationEngine](super::declaration_engine::DeclarationEngine)
+/// when inserting a `T`, or after checking the kind of a [DeclarationId], so
+/// looking up a [DeclId] can not find a different kind of declaration.
+pub struct DeclId<T> {
+    id: DeclarationId,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for DeclId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DeclId<T> {}
+
+impl<T> fmt::Debug for DeclId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.id)
+    }
+}
+
+impl<T> fmt::Display for DeclId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl<T> PartialEq for DeclId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for DeclId<T> {}
+
+impl<T> Hash for DeclId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> std::ops::Deref for DeclId<T> {
+    type Target = usize;
+    fn deref(&self) -> &Self::Target {
+        &self.id
+    }
+}
+
+impl<T> CopyTypes for DeclId<T> {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.id.copy_types(session, type_mapping)
+    }
+}
+
+impl<T> DeclId<T> {
+    pub(super) fn new(id: DeclarationId) -> DeclId<T> {
+        DeclId {
+            id,
+            kind: PhantomData,
+        }
+    }
+
+    /// The same id, without the kind of declaration that it refers to.
+    pub(crate) fn untyped(&self) -> DeclarationId {
+        self.id
+    }
 }
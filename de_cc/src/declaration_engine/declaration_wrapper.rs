@@ -1,30 +1,31 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
 use crate::{
     language::ty::typed_declaration::{
         TyFunctionDeclaration, TyStructDeclaration, TyTraitDeclaration, TyTraitFn, TyTraitImpl,
     },
+    session::Session,
     type_system::type_mapping::TypeMapping,
     types::copy_types::CopyTypes,
 };
 
+use super::declaration_id::DeclarationId;
+
 /// The [DeclarationWrapper] type is used in the [DeclarationEngine]
 /// as a means of placing all declaration types into the same type.
-#[derive(Clone)]
+///
+/// The declarations are shared behind an [Arc], so that getting one out of the
+/// wrapper does not copy it.
+#[derive(Clone, Default)]
 pub(crate) enum DeclarationWrapper {
     // no-op variant to fulfill the default trait
+    #[default]
     Unknown,
-    Function(TyFunctionDeclaration),
-    Trait(TyTraitDeclaration),
-    TraitFn(TyTraitFn),
-    TraitImpl(TyTraitImpl),
-    Struct(TyStructDeclaration),
-}
-
-impl Default for DeclarationWrapper {
-    fn default() -> Self {
-        DeclarationWrapper::Unknown
-    }
+    Function(Arc<TyFunctionDeclaration>),
+    Trait(Arc<TyTraitDeclaration>),
+    TraitFn(Arc<TyTraitFn>),
+    TraitImpl(Arc<TyTraitImpl>),
+    Struct(Arc<TyStructDeclaration>),
 }
 
 // NOTE: Hash and PartialEq must uphold the invariant:
@@ -58,19 +59,50 @@ impl fmt::Display for DeclarationWrapper {
 }
 
 impl CopyTypes for DeclarationWrapper {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         match self {
             DeclarationWrapper::Unknown => {}
-            DeclarationWrapper::Function(decl) => decl.copy_types(type_mapping),
-            DeclarationWrapper::Trait(decl) => decl.copy_types(type_mapping),
-            DeclarationWrapper::TraitFn(decl) => decl.copy_types(type_mapping),
-            DeclarationWrapper::TraitImpl(decl) => decl.copy_types(type_mapping),
-            DeclarationWrapper::Struct(decl) => decl.copy_types(type_mapping),
+            DeclarationWrapper::Function(decl) => {
+                Arc::make_mut(decl).copy_types(session, type_mapping)
+            }
+            DeclarationWrapper::Trait(decl) => {
+                Arc::make_mut(decl).copy_types(session, type_mapping)
+            }
+            DeclarationWrapper::TraitFn(decl) => {
+                Arc::make_mut(decl).copy_types(session, type_mapping)
+            }
+            DeclarationWrapper::TraitImpl(decl) => {
+                Arc::make_mut(decl).copy_types(session, type_mapping)
+            }
+            DeclarationWrapper::Struct(decl) => {
+                Arc::make_mut(decl).copy_types(session, type_mapping)
+            }
         }
     }
 }
 
 impl DeclarationWrapper {
+    /// The declarations that are declared as part of this one, which are the
+    /// trait fns of a trait and the methods of a trait impl.
+    pub(crate) fn children(&self) -> Vec<DeclarationId> {
+        match self {
+            DeclarationWrapper::Trait(decl) => decl
+                .interface_surface
+                .iter()
+                .map(|trait_fn| trait_fn.inner_ref().untyped())
+                .collect(),
+            DeclarationWrapper::TraitImpl(decl) => decl
+                .methods
+                .iter()
+                .map(|method| method.inner_ref().untyped())
+                .collect(),
+            DeclarationWrapper::Unknown
+            | DeclarationWrapper::Function(_)
+            | DeclarationWrapper::TraitFn(_)
+            | DeclarationWrapper::Struct(_) => vec![],
+        }
+    }
+
     /// friendly name string used for error reporting.
     fn friendly_name(&self) -> &'static str {
         match self {
@@ -83,9 +115,9 @@ impl DeclarationWrapper {
         }
     }
 
-    pub(super) fn expect_function(self) -> Result<TyFunctionDeclaration, String> {
+    fn expect_function(&self) -> Result<Arc<TyFunctionDeclaration>, String> {
         match self {
-            DeclarationWrapper::Function(decl) => Ok(decl),
+            DeclarationWrapper::Function(decl) => Ok(decl.clone()),
             DeclarationWrapper::Unknown => {
                 Err("did not expect to find unknown declaration".to_string())
             }
@@ -96,9 +128,9 @@ impl DeclarationWrapper {
         }
     }
 
-    pub(super) fn expect_trait(self) -> Result<TyTraitDeclaration, String> {
+    fn expect_trait(&self) -> Result<Arc<TyTraitDeclaration>, String> {
         match self {
-            DeclarationWrapper::Trait(decl) => Ok(decl),
+            DeclarationWrapper::Trait(decl) => Ok(decl.clone()),
             DeclarationWrapper::Unknown => {
                 Err("did not expect to find unknown declaration".to_string())
             }
@@ -109,9 +141,9 @@ impl DeclarationWrapper {
         }
     }
 
-    pub(super) fn expect_trait_fn(self) -> Result<TyTraitFn, String> {
+    fn expect_trait_fn(&self) -> Result<Arc<TyTraitFn>, String> {
         match self {
-            DeclarationWrapper::TraitFn(decl) => Ok(decl),
+            DeclarationWrapper::TraitFn(decl) => Ok(decl.clone()),
             DeclarationWrapper::Unknown => {
                 Err("did not expect to find unknown declaration".to_string())
             }
@@ -122,9 +154,9 @@ impl DeclarationWrapper {
         }
     }
 
-    pub(super) fn expect_trait_impl(self) -> Result<TyTraitImpl, String> {
+    fn expect_trait_impl(&self) -> Result<Arc<TyTraitImpl>, String> {
         match self {
-            DeclarationWrapper::TraitImpl(decl) => Ok(decl),
+            DeclarationWrapper::TraitImpl(decl) => Ok(decl.clone()),
             DeclarationWrapper::Unknown => {
                 Err("did not expect to find unknown declaration".to_string())
             }
@@ -135,9 +167,9 @@ impl DeclarationWrapper {
         }
     }
 
-    pub(super) fn expect_struct(self) -> Result<TyStructDeclaration, String> {
+    fn expect_struct(&self) -> Result<Arc<TyStructDeclaration>, String> {
         match self {
-            DeclarationWrapper::Struct(decl) => Ok(decl),
+            DeclarationWrapper::Struct(decl) => Ok(decl.clone()),
             DeclarationWrapper::Unknown => {
                 Err("did not expect to find unknown declaration".to_string())
             }
@@ -148,3 +180,60 @@ impl DeclarationWrapper {
         }
     }
 }
+
+/// A kind of declaration that is stored in the [DeclarationEngine](super::declaration_engine::DeclarationEngine),
+/// which [DeclId](super::declaration_id::DeclId)s can refer to.
+pub(crate) trait Declaration: Sized {
+    fn into_wrapper(self) -> DeclarationWrapper;
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String>;
+}
+
+impl Declaration for TyFunctionDeclaration {
+    fn into_wrapper(self) -> DeclarationWrapper {
+        DeclarationWrapper::Function(Arc::new(self))
+    }
+
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String> {
+        wrapper.expect_function()
+    }
+}
+
+impl Declaration for TyTraitDeclaration {
+    fn into_wrapper(self) -> DeclarationWrapper {
+        DeclarationWrapper::Trait(Arc::new(self))
+    }
+
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String> {
+        wrapper.expect_trait()
+    }
+}
+
+impl Declaration for TyTraitFn {
+    fn into_wrapper(self) -> DeclarationWrapper {
+        DeclarationWrapper::TraitFn(Arc::new(self))
+    }
+
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String> {
+        wrapper.expect_trait_fn()
+    }
+}
+
+impl Declaration for TyTraitImpl {
+    fn into_wrapper(self) -> DeclarationWrapper {
+        DeclarationWrapper::TraitImpl(Arc::new(self))
+    }
+
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String> {
+        wrapper.expect_trait_impl()
+    }
+}
+
+impl Declaration for TyStructDeclaration {
+    fn into_wrapper(self) -> DeclarationWrapper {
+        DeclarationWrapper::Struct(Arc::new(self))
+    }
+
+    fn from_wrapper(wrapper: &DeclarationWrapper) -> Result<Arc<Self>, String> {
+        wrapper.expect_struct()
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{collection_context::collection_index::CollectionIndex, type_system::type_id::TypeId};
+
+use super::declaration_id::DeclarationId;
+
+/// A monomorphized copy of a generic declaration, and why it was made.
+#[derive(Clone, Debug)]
+pub(crate) struct Instantiation {
+    /// The generic declaration that this is a copy of.
+    #[allow(dead_code)]
+    pub(crate) original_id: DeclarationId,
+    pub(crate) copy_id: DeclarationId,
+    /// The types that replace the type parameters of the original declaration,
+    /// in order. Copies made during type inference start out with new type
+    /// variables, which are only known once the uses of the copy are inferred.
+    #[allow(dead_code)]
+    pub(crate) type_arguments: Vec<TypeId>,
+    /// The nodes whose type inference or instance collection asked for this
+    /// copy, the first of which made it.
+    pub(crate) requested_by: Vec<CollectionIndex>,
+}
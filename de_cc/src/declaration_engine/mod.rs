@@ -2,3 +2,4 @@
 pub(crate) mod declaration_engine;
 pub(crate) mod declaration_id;
 pub(crate) mod declaration_wrapper;
+pub(crate) mod instantiation;
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection_context::{
+        collection_context::CollectionContext,
+        collection_index::{CCIdx, CollectionIndex},
+    },
+    declaration_engine::declaration_engine::de_remove,
+    language::{
+        parsed::{Application, Node},
+        resolved::ResolvedApplication,
+        ty::{TyFile, TyNode},
+    },
+    query_engine::{query_cache::QueryCache, query_engine::QueryEngine},
+    semantic_analysis::graph_collection::{
+        add_graph_application, add_graph_file, collect_graph_node,
+    },
+    session::Session,
+};
+
+use super::node_summary::NodeSummary;
+
+/// Compiles successive versions of the same [Application], analyzing again only
+/// the file-level nodes whose inputs changed since the previous version.
+///
+/// The [CollectionContext], the [Session], and the results of every query are
+/// kept between versions. Every file-level node is
+/// fingerprinted, and a node is reused from the previous version when a node
+/// with the same fingerprint appears in the same file, in the same order
+/// relative to the other reused nodes, and none of the nodes that it depends on
+/// changed. Every other node is collected and analyzed again from scratch, and
+/// the nodes of the previous version that are not reused are removed, along with
+/// their declarations and query results.
+#[derive(Default)]
+pub struct IncrementalCompiler {
+    session: Session,
+    cc: CollectionContext,
+    query_cache: QueryCache,
+    /// The files of the previously compiled version, by name.
+    files: HashMap<String, CompiledFile>,
+    /// The application node of the previously compiled version.
+    application: Option<CollectionIndex>,
+}
+
+struct CompiledFile {
+    file: CCIdx<TyFile>,
+    nodes: Vec<CompiledNode>,
+}
+
+struct CompiledNode {
+    summary: NodeSummary,
+    node: CCIdx<TyNode>,
+}
+
+/// A file-level node of the version being compiled.
+struct PendingNode {
+    summary: NodeSummary,
+    parsed: Node,
+    /// The matching node from the previous version, if there is one.
+    previous: Option<CCIdx<TyNode>>,
+    dirty: bool,
+}
+
+struct PendingFile {
+    name: String,
+    nodes: Vec<PendingNode>,
+    previous: Option<CCIdx<TyFile>>,
+    /// Whether any node of the previous version of this file is gone.
+    has_removed_nodes: bool,
+}
+
+impl IncrementalCompiler {
+    pub fn new() -> IncrementalCompiler {
+        IncrementalCompiler::default()
+    }
+
+    /// The session that the versions are compiled in.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Compiles the next version of the application, returning the first error
+    /// found.
+    pub fn compile(&mut self, application: Application) -> Result<ResolvedApplication, String> {
+        // the session is moved out of `self` while the version is compiled, as
+        // compiling needs `self` mutably
+        let session = std::mem::take(&mut self.session);
+        let resolved_application = session.enter(|| self.compile_in_session(&session, application));
+        self.session = session;
+        resolved_application
+    }
+
+    fn compile_in_session(
+        &mut self,
+        session: &Session,
+        application: Application,
+    ) -> Result<ResolvedApplication, String> {
+        // the names declared by the nodes that changed, and the names used by them
+        let mut changed_declares = HashSet::new();
+        let mut changed_uses = HashSet::new();
+
+        // 1. match the nodes of every file against the previous version
+        let mut previous_files = std::mem::take(&mut self.files);
+        let mut files = vec![];
+        for file in application.files.into_iter() {
+            let previous = previous_files.remove(&file.name);
+            let (previous_file, previous_nodes) = match previous {
+                Some(CompiledFile { file, nodes }) => (Some(file), nodes),
+                None => (None, vec![]),
+            };
+            let (mut nodes, removed) = match_nodes(previous_nodes, file.nodes);
+
+            // a changed star import can change what any name in the file refers to
+            let star_imports_changed = removed.iter().any(|summary| summary.is_star_import)
+                || nodes
+                    .iter()
+                    .any(|node| node.dirty && node.summary.is_star_import);
+            for node in nodes.iter_mut() {
+                node.dirty |= star_imports_changed;
+                if node.dirty {
+                    changed_declares.extend(node.summary.declares.iter().cloned());
+                    changed_uses.extend(node.summary.uses.iter().cloned());
+                }
+            }
+            for summary in removed.iter() {
+                changed_declares.extend(summary.declares.iter().cloned());
+                changed_uses.extend(summary.uses.iter().cloned());
+            }
+
+            files.push(PendingFile {
+                name: file.name,
+                nodes,
+                previous: previous_file,
+                has_removed_nodes: !removed.is_empty(),
+            });
+        }
+        for (name, file) in previous_files.into_iter() {
+            self.cc.unregister_file_index(&name);
+            for node in file.nodes.into_iter() {
+                changed_declares.extend(node.summary.declares);
+                changed_uses.extend(node.summary.uses);
+            }
+        }
+
+        // 2. find every node that depends on a changed node. A generic node also
+        //    depends on the nodes that use it, as they determine its monomorphized
+        //    copies
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for node in files.iter_mut().flat_map(|file| file.nodes.iter_mut()) {
+                if !node.dirty
+                    && (node.summary.uses_any(&changed_declares)
+                        || (node.summary.is_generic && node.summary.declares_any(&changed_uses)))
+                {
+                    node.dirty = true;
+                    changed_declares.extend(node.summary.declares.iter().cloned());
+                    changed_uses.extend(node.summary.uses.iter().cloned());
+                    progress = true;
+                }
+            }
+        }
+
+        // 3. collect the dirty nodes and move the reused nodes into the new files
+        let mut file_idxs = vec![];
+        for file in files.into_iter() {
+            let (file_idx, summaries) = self.collect_file(session, file);
+            self.files.insert(
+                file_idx.inner_ref().name.clone(),
+                CompiledFile {
+                    nodes: summaries
+                        .into_iter()
+                        .zip(file_idx.inner_ref().nodes.iter().cloned())
+                        .map(|(summary, node)| CompiledNode { summary, node })
+                        .collect(),
+                    file: file_idx.clone(),
+                },
+            );
+            file_idxs.push(file_idx);
+        }
+
+        // 4. remove what is left of the previous version, now that the reused
+        //    nodes have been moved out of it
+        if let Some(previous) = self.application.take() {
+            let removed = self.cc.remove_tree(previous);
+            self.query_cache
+                .forget(&removed.iter().map(|(index, _)| *index).collect());
+            for (_, node) in removed.into_iter() {
+                if let Some(decl_id) = node.declaration_id() {
+                    de_remove(session, decl_id)?;
+                }
+            }
+        }
+        let application = add_graph_application(session, &mut self.cc, file_idxs)?;
+        self.application = Some(application.idx());
+
+        // 5. query the resolved form of every root declaration, reusing the
+        //    results of the queries made for the reused nodes
+        self.query_cache.forget_impls();
+        QueryEngine::new(session, &self.cc, &application, &mut self.query_cache)
+            .resolved_application()
+    }
+
+    fn collect_file(
+        &mut self,
+        session: &Session,
+        file: PendingFile,
+    ) -> (CCIdx<TyFile>, Vec<NodeSummary>) {
+        let summaries = file.nodes.iter().map(|node| node.summary.clone()).collect();
+
+        // reuse the entire file if none of its nodes changed
+        if let Some(previous) = &file.previous {
+            if !file.has_removed_nodes && file.nodes.iter().all(|node| !node.dirty) {
+                self.cc.detach(previous.idx());
+                return (previous.clone(), summaries);
+            }
+        }
+
+        let nodes = file
+            .nodes
+            .into_iter()
+            .map(|node| match node.previous {
+                Some(previous) if !node.dirty => {
+                    self.cc.detach(previous.idx());
+                    previous
+                }
+                _ => collect_graph_node(session, &mut self.cc, &file.name, &[], &[], node.parsed),
+            })
+            .collect();
+        (add_graph_file(&mut self.cc, file.name, nodes), summaries)
+    }
+}
+
+/// Matches every node in `nodes` with a node of the same fingerprint in
+/// `previous`, keeping the matched nodes in the same relative order. Returns the
+/// nodes, with the unmatched ones marked as dirty, and the summaries of the
+/// previous nodes that were not matched.
+fn match_nodes(
+    previous: Vec<CompiledNode>,
+    nodes: Vec<Node>,
+) -> (Vec<PendingNode>, Vec<NodeSummary>) {
+    let mut previous = previous.into_iter().map(Some).collect::<Vec<_>>();
+    let mut next = 0;
+    let pending = nodes
+        .into_iter()
+        .map(|parsed| {
+            let summary = NodeSummary::new(&parsed);
+            let position = (next..previous.len()).find(|i| {
+                previous[*i]
+                    .as_ref()
+                    .map(|node| node.summary.fingerprint == summary.fingerprint)
+                    .unwrap_or(false)
+            });
+            let matched = position.and_then(|i| {
+                next = i + 1;
+                previous[i].take()
+            });
+            PendingNode {
+                dirty: matched.is_none(),
+                previous: matched.map(|node| node.node),
+                summary,
+                parsed,
+            }
+        })
+        .collect();
+    let removed = previous
+        .into_iter()
+        .flatten()
+        .map(|node| node.summary)
+        .collect();
+    (pending, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        language::parsed::{
+            constructors::*, declaration::constructors::*, expression::constructors::*, File,
+        },
+        type_system::type_info::constructors::*,
+    };
+
+    /// Builds an application with a single file, where `value` is the only
+    /// thing that changes between edits.
+    fn edit(value: u64) -> Application {
+        let nodes = vec![
+            struct_(
+                "Point",
+                &[type_param("T", None)],
+                &[struct_field("x", t_gen_("T"))],
+            ),
+            func_decl(
+                "id",
+                &[type_param("T", None)],
+                &[func_param("x", t_gen_("T"))],
+                &[return_(var("x"))],
+                t_gen_("T"),
+            ),
+            func_decl(
+                "main",
+                &[],
+                &[],
+                &[
+                    var_decl("a", None, func_app("id", &[], &[u64(value)])),
+                    var_decl(
+                        "p",
+                        None,
+                        struct_exp("Point", &[], &[struct_exp_field("x", u8(2u8))]),
+                    ),
+                ],
+                t_unit(),
+            ),
+        ];
+        Application {
+            files: vec![File {
+                name: "main.sw".to_string(),
+                nodes,
+            }],
+        }
+    }
+
+    #[test]
+    fn repeated_edits_size_test() {
+        let mut compiler = IncrementalCompiler::new();
+        let mut sizes = vec![];
+        for value in 0..10 {
+            compiler.compile(edit(value)).unwrap();
+            sizes.push((
+                compiler.cc.node_count(),
+                compiler.session.declaration_engine.len(),
+            ));
+        }
+        assert!(sizes.iter().all(|size| *size == sizes[0]), "{:?}", sizes);
+    }
+}
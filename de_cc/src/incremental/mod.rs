@@ -0,0 +1,5 @@
+//! This module recompiles an application after edits, reusing the analysis of
+//! the file-level nodes that were not affected by those edits.
+
+pub(crate) mod incremental_compiler;
+mod node_summary;
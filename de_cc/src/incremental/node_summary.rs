@@ -0,0 +1,212 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    language::parsed::{
+        declaration::{Declaration, FunctionDeclaration, TypeParameter},
+        expression::Expression,
+        Node,
+    },
+    type_system::type_info::TypeInfo,
+};
+
+/// What the incremental compiler needs to know about a file-level node in
+/// order to decide whether it must be analyzed again.
+///
+/// Dependencies between nodes are tracked by name. A node depends on every
+/// node that declares a name that it uses, which over-approximates the
+/// declarations that it actually resolves to, but also catches a newly added
+/// declaration that would shadow the one it resolved to before.
+#[derive(Clone)]
+pub(super) struct NodeSummary {
+    /// A hash of the parsed node.
+    pub(super) fingerprint: u64,
+    /// The symbols and methods that the node declares.
+    pub(super) declares: HashSet<String>,
+    /// The symbols, methods, types, and variables that the node refers to.
+    pub(super) uses: HashSet<String>,
+    /// Whether the node is resolved into monomorphized copies, in which case its
+    /// resolved form depends on the nodes that use it.
+    pub(super) is_generic: bool,
+    pub(super) is_star_import: bool,
+}
+
+impl NodeSummary {
+    pub(super) fn new(node: &Node) -> NodeSummary {
+        let mut hasher = DefaultHasher::new();
+        node.hash(&mut hasher);
+
+        let mut summary = NodeSummary {
+            fingerprint: hasher.finish(),
+            declares: HashSet::new(),
+            uses: HashSet::new(),
+            is_generic: false,
+            is_star_import: false,
+        };
+        match node {
+            Node::StarImport(_) => summary.is_star_import = true,
+            Node::Declaration(decl) => summary.summarize_declaration(decl),
+            Node::Expression(exp) | Node::ReturnStatement(exp) => summary.summarize_expression(exp),
+        }
+        summary
+    }
+
+    /// Whether any of the names in `names` is declared by this node.
+    pub(super) fn declares_any(&self, names: &HashSet<String>) -> bool {
+        !self.declares.is_disjoint(names)
+    }
+
+    /// Whether any of the names in `names` is used by this node.
+    pub(super) fn uses_any(&self, names: &HashSet<String>) -> bool {
+        !self.uses.is_disjoint(names)
+    }
+
+    fn summarize_declaration(&mut self, decl: &Declaration) {
+        match decl {
+            Declaration::Variable(var_decl) => {
+                self.declares.insert(var_decl.name.clone());
+                self.summarize_type(&var_decl.type_ascription);
+                self.summarize_expression(&var_decl.body);
+            }
+            Declaration::Function(func_decl) => {
+                self.declares.insert(func_decl.name.clone());
+                self.is_generic = !func_decl.type_parameters.is_empty();
+                self.summarize_function(func_decl);
+            }
+            Declaration::Trait(trait_decl) => {
+                self.declares.insert(trait_decl.name.clone());
+                for trait_fn in trait_decl.interface_surface.iter() {
+                    self.declares.insert(trait_fn.name.clone());
+                    for param in trait_fn.parameters.iter() {
+                        self.summarize_type(&param.type_info);
+                    }
+                    self.summarize_type(&trait_fn.return_type);
+                }
+            }
+            Declaration::TraitImpl(trait_impl) => {
+                self.uses.insert(trait_impl.trait_name.clone());
+                self.summarize_type(&trait_impl.type_implementing_for);
+                self.summarize_type_parameters(&trait_impl.type_parameters);
+                self.is_generic = !trait_impl.type_parameters.is_empty();
+                for method in trait_impl.methods.iter() {
+                    self.declares.insert(method.name.clone());
+                    self.is_generic |= !method.type_parameters.is_empty();
+                    self.summarize_function(method);
+                }
+            }
+            Declaration::Struct(struct_decl) => {
+                self.declares.insert(struct_decl.name.clone());
+                self.is_generic = !struct_decl.type_parameters.is_empty();
+                for field in struct_decl.fields.iter() {
+                    self.summarize_type(&field.type_info);
+                }
+            }
+        }
+    }
+
+    fn summarize_function(&mut self, func_decl: &FunctionDeclaration) {
+        self.summarize_type_parameters(&func_decl.type_parameters);
+        for param in func_decl.parameters.iter() {
+            self.summarize_type(&param.type_info);
+        }
+        self.summarize_type(&func_decl.return_type);
+        for node in func_decl.body.iter() {
+            match node {
+                Node::StarImport(_) => {}
+                Node::Declaration(decl) => {
+                    // declarations in the body are not visible from outside of
+                    // the function, so only what they use matters
+                    let declares = std::mem::take(&mut self.declares);
+                    let is_generic = self.is_generic;
+                    self.summarize_declaration(decl);
+                    self.declares = declares;
+                    self.is_generic = is_generic;
+                }
+                Node::Expression(exp) | Node::ReturnStatement(exp) => {
+                    self.summarize_expression(exp)
+                }
+            }
+        }
+    }
+
+    fn summarize_type_parameters(&mut self, type_parameters: &[TypeParameter]) {
+        for type_param in type_parameters.iter() {
+            if let Some(constraint) = &type_param.trait_constraint {
+                self.uses.insert(constraint.trait_name.clone());
+            }
+        }
+    }
+
+    fn summarize_expression(&mut self, exp: &Expression) {
+        match exp {
+            Expression::Literal { .. } => {}
+            Expression::Variable { name } => {
+                self.uses.insert(name.clone());
+            }
+            Expression::FunctionApplication {
+                name,
+                type_arguments,
+                arguments,
+            } => {
+                self.uses.insert(name.clone());
+                for type_arg in type_arguments.iter() {
+                    self.summarize_type(type_arg);
+                }
+                for argument in arguments.iter() {
+                    self.summarize_expression(argument);
+                }
+            }
+            Expression::MethodCall {
+                parent_name,
+                func_name,
+                type_arguments,
+                arguments,
+            } => {
+                self.uses.insert(parent_name.clone());
+                self.uses.insert(func_name.clone());
+                for type_arg in type_arguments.iter() {
+                    self.summarize_type(type_arg);
+                }
+                for argument in arguments.iter() {
+                    self.summarize_expression(argument);
+                }
+            }
+            Expression::Struct {
+                struct_name,
+                type_arguments,
+                fields,
+            } => {
+                self.uses.insert(struct_name.clone());
+                for type_arg in type_arguments.iter() {
+                    self.summarize_type(type_arg);
+                }
+                for field in fields.iter() {
+                    self.summarize_expression(&field.value);
+                }
+            }
+        }
+    }
+
+    fn summarize_type(&mut self, type_info: &TypeInfo) {
+        match type_info {
+            TypeInfo::Custom {
+                name,
+                type_arguments,
+            } => {
+                self.uses.insert(name.clone());
+                for type_arg in type_arguments.iter() {
+                    self.summarize_type(type_arg);
+                }
+            }
+            // parsed types are not resolved into structs yet
+            TypeInfo::Struct { .. }
+            | TypeInfo::ErrorRecovery
+            | TypeInfo::Unknown
+            | TypeInfo::UnknownGeneric { .. }
+            | TypeInfo::Unit
+            | TypeInfo::UnsignedInteger(_) => {}
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt;
 
 use crate::type_system::{type_info::TypeInfo, IntegerBits};
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Literal {
     U8(u8),
     U16(u16),
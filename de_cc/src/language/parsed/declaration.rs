@@ -2,11 +2,11 @@ use indent_write::fmt::IndentWriter;
 use std::fmt;
 use std::fmt::Write;
 
-use crate::type_system::{type_info::TypeInfo, type_parameter::TypeParameter};
+use crate::type_system::{trait_constraint::TraitConstraint, type_info::TypeInfo};
 
 use super::{expression::*, Node};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Hash)]
 pub enum Declaration {
     Variable(VariableDeclaration),
     Function(FunctionDeclaration),
@@ -28,7 +28,7 @@ impl fmt::Display for Declaration {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Hash, Debug)]
 pub struct VariableDeclaration {
     pub(crate) name: String,
     pub(crate) type_ascription: TypeInfo,
@@ -45,7 +45,7 @@ impl fmt::Display for VariableDeclaration {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Hash)]
 pub struct FunctionDeclaration {
     pub(crate) name: String,
     pub(crate) type_parameters: Vec<TypeParameter>,
@@ -88,7 +88,7 @@ impl fmt::Display for FunctionDeclaration {
                         .filter(|x| x.trait_constraint.is_some())
                         .map(|x| format!(
                             "{}: {}",
-                            x.type_id,
+                            x.name,
                             x.trait_constraint.clone().unwrap().trait_name
                         ))
                         .collect::<Vec<_>>()
@@ -119,7 +119,21 @@ impl fmt::Display for FunctionParameter {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// A type parameter, which is only given a type once its declaration is
+/// collected into the Ty AST.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TypeParameter {
+    pub(crate) name: String,
+    pub(crate) trait_constraint: Option<TraitConstraint>,
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct TraitDeclaration {
     pub(crate) name: String,
     pub(crate) interface_surface: Vec<TraitFn>,
@@ -138,7 +152,7 @@ impl fmt::Display for TraitDeclaration {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct TraitFn {
     pub(crate) name: String,
     pub(crate) parameters: Vec<FunctionParameter>,
@@ -161,7 +175,7 @@ impl fmt::Display for TraitFn {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Hash)]
 pub struct TraitImpl {
     pub(crate) trait_name: String,
     pub(crate) type_implementing_for: TypeInfo,
@@ -200,7 +214,7 @@ impl fmt::Display for TraitImpl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct StructDeclaration {
     pub(crate) name: String,
     pub(crate) type_parameters: Vec<TypeParameter>,
@@ -266,15 +280,12 @@ pub struct EnumVariant {
 pub mod constructors {
     use crate::{
         language::parsed::{Expression, Node},
-        type_system::{
-            trait_constraint::TraitConstraint, type_engine::insert_type, type_info::TypeInfo,
-            type_parameter::TypeParameter,
-        },
+        type_system::{trait_constraint::TraitConstraint, type_info::TypeInfo},
     };
 
     use super::{
         Declaration, FunctionDeclaration, FunctionParameter, StructDeclaration, StructField,
-        TraitDeclaration, TraitFn, TraitImpl, VariableDeclaration,
+        TraitDeclaration, TraitFn, TraitImpl, TypeParameter, VariableDeclaration,
     };
 
     pub fn star_import(name: &str) -> Node {
@@ -331,9 +342,6 @@ pub mod constructors {
     pub fn type_param(name: &str, trait_constraint: Option<&str>) -> TypeParameter {
         TypeParameter {
             name: name.to_string(),
-            type_id: insert_type(TypeInfo::UnknownGeneric {
-                name: name.to_string(),
-            }),
             trait_constraint: trait_constraint.map(|x| TraitConstraint {
                 trait_name: x.to_string(),
             }),
@@ -2,9 +2,9 @@ use indent_write::fmt::IndentWriter;
 use std::fmt;
 use std::fmt::Write;
 
-use crate::{language::literal::Literal, type_system::type_argument::TypeArgument};
+use crate::{language::literal::Literal, type_system::type_info::TypeInfo};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Hash, Debug)]
 pub enum Expression {
     Literal {
         value: Literal,
@@ -14,18 +14,18 @@ pub enum Expression {
     },
     FunctionApplication {
         name: String,
-        type_arguments: Vec<TypeArgument>,
+        type_arguments: Vec<TypeInfo>,
         arguments: Vec<Expression>,
     },
     MethodCall {
         parent_name: String,
         func_name: String,
-        type_arguments: Vec<TypeArgument>,
+        type_arguments: Vec<TypeInfo>,
         arguments: Vec<Expression>,
     },
     Struct {
         struct_name: String,
-        type_arguments: Vec<TypeArgument>,
+        type_arguments: Vec<TypeInfo>,
         fields: Vec<StructExpressionField>,
     },
 }
@@ -128,7 +128,7 @@ impl fmt::Display for Expression {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Hash, Debug)]
 pub struct StructExpressionField {
     pub(crate) name: String,
     pub(crate) value: Expression,
@@ -141,12 +141,7 @@ impl fmt::Display for StructExpressionField {
 }
 
 pub mod constructors {
-    use std::u8;
-
-    use crate::{
-        language::literal::Literal,
-        type_system::{type_argument::TypeArgument, type_engine::insert_type, type_info::TypeInfo},
-    };
+    use crate::{language::literal::Literal, type_system::type_info::TypeInfo};
 
     use super::{Expression, StructExpressionField};
 
@@ -182,7 +177,7 @@ pub mod constructors {
 
     pub fn func_app(
         name: &str,
-        type_arguments: &[TypeArgument],
+        type_arguments: &[TypeInfo],
         arguments: &[Expression],
     ) -> Expression {
         Expression::FunctionApplication {
@@ -199,13 +194,7 @@ pub mod constructors {
     ) -> Expression {
         Expression::Struct {
             struct_name: struct_name.to_string(),
-            type_arguments: type_arguments
-                .iter()
-                .cloned()
-                .map(|type_info| TypeArgument {
-                    type_id: insert_type(type_info),
-                })
-                .collect(),
+            type_arguments: type_arguments.to_vec(),
             fields: fields.to_vec(),
         }
     }
@@ -220,7 +209,7 @@ pub mod constructors {
     pub fn method_app(
         parent_name: &str,
         func_name: &str,
-        type_arguments: &[TypeArgument],
+        type_arguments: &[TypeInfo],
         arguments: &[Expression],
     ) -> Expression {
         Expression::MethodCall {
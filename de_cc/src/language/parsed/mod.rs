@@ -56,7 +56,7 @@ impl fmt::Display for File {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Hash)]
 pub enum Node {
     StarImport(String),
     Declaration(Declaration),
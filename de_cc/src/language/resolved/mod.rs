@@ -10,6 +10,20 @@ pub struct ResolvedApplication {
     pub files: Vec<ResolvedFile>,
 }
 
+impl ResolvedApplication {
+    /// The name and the mangled name of every function, trait, and struct, in
+    /// the order they are declared.
+    pub fn symbols(&self) -> Vec<(String, String)> {
+        let mut symbols = vec![];
+        for file in self.files.iter() {
+            for node in file.nodes.iter() {
+                node.collect_symbols(&mut symbols);
+            }
+        }
+        symbols
+    }
+}
+
 impl fmt::Display for ResolvedApplication {
     #[allow(clippy::useless_format)]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -60,6 +74,14 @@ pub(crate) enum ResolvedNode {
     ReturnStatement(ResolvedExpression),
 }
 
+impl ResolvedNode {
+    fn collect_symbols(&self, symbols: &mut Vec<(String, String)>) {
+        if let ResolvedNode::Declaration(declaration) = self {
+            declaration.collect_symbols(symbols);
+        }
+    }
+}
+
 impl fmt::Display for ResolvedNode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
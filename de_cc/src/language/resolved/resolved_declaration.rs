@@ -14,6 +14,26 @@ pub(crate) enum ResolvedDeclaration {
     Struct(ResolvedStructDeclaration),
 }
 
+impl ResolvedDeclaration {
+    pub(super) fn collect_symbols(&self, symbols: &mut Vec<(String, String)>) {
+        match self {
+            ResolvedDeclaration::Variable(_) => {}
+            ResolvedDeclaration::Function(decl) => decl.collect_symbols(symbols),
+            ResolvedDeclaration::Trait(decl) => {
+                symbols.push((decl.name.clone(), decl.mangled_name.clone()));
+            }
+            ResolvedDeclaration::TraitImpl(decl) => {
+                for method in decl.methods.iter() {
+                    method.collect_symbols(symbols);
+                }
+            }
+            ResolvedDeclaration::Struct(decl) => {
+                symbols.push((decl.name.clone(), decl.mangled_name.clone()));
+            }
+        }
+    }
+}
+
 impl fmt::Display for ResolvedDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -44,12 +64,22 @@ impl fmt::Display for ResolvedVariableDeclaration {
 
 pub(crate) struct ResolvedFunctionDeclaration {
     pub(crate) name: String,
+    pub(crate) mangled_name: String,
     pub(crate) type_parameters: Vec<ResolvedTypeParameter>,
     pub(crate) parameters: Vec<ResolvedFunctionParameter>,
     pub(crate) body: ResolvedCodeBlock,
     pub(crate) return_type: ResolvedType,
 }
 
+impl ResolvedFunctionDeclaration {
+    fn collect_symbols(&self, symbols: &mut Vec<(String, String)>) {
+        symbols.push((self.name.clone(), self.mangled_name.clone()));
+        for node in self.body.contents.iter() {
+            node.collect_symbols(symbols);
+        }
+    }
+}
+
 impl fmt::Display for ResolvedFunctionDeclaration {
     fn fmt(&self, mut f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
@@ -105,6 +135,7 @@ pub(crate) struct ResolvedCodeBlock {
 #[derive(Clone)]
 pub(crate) struct ResolvedTraitDeclaration {
     pub(crate) name: String,
+    pub(crate) mangled_name: String,
     pub(crate) interface_surface: Vec<ResolvedTraitFn>,
 }
 
@@ -170,6 +201,7 @@ impl fmt::Display for ResolvedTraitImpl {
 
 pub(crate) struct ResolvedStructDeclaration {
     pub(crate) name: String,
+    pub(crate) mangled_name: String,
     pub(crate) type_parameters: Vec<ResolvedTypeParameter>,
     pub(crate) fields: Vec<ResolvedStructField>,
 }
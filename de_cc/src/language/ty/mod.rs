@@ -5,8 +5,8 @@ use std::fmt;
 use colored::Colorize;
 
 use crate::{
-    collection_context::collection_index::CCIdx, type_system::type_mapping::TypeMapping,
-    types::copy_types::CopyTypes,
+    collection_context::collection_index::CCIdx, session::Session,
+    type_system::type_mapping::TypeMapping, types::copy_types::CopyTypes,
 };
 
 use self::{typed_declaration::TyDeclaration, typed_expression::TyExpression};
@@ -54,10 +54,10 @@ impl fmt::Display for TyApplication {
 }
 
 impl CopyTypes for TyApplication {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.files
             .iter_mut()
-            .for_each(|file| file.copy_types(type_mapping));
+            .for_each(|file| file.copy_types(session, type_mapping));
     }
 }
 
@@ -110,10 +110,10 @@ impl fmt::Display for TyFile {
 }
 
 impl CopyTypes for TyFile {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.nodes
             .iter_mut()
-            .for_each(|node| node.copy_types(type_mapping));
+            .for_each(|node| node.copy_types(session, type_mapping));
     }
 }
 
@@ -148,11 +148,11 @@ impl fmt::Display for TyNode {
 }
 
 impl CopyTypes for TyNode {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         match self {
-            TyNode::Declaration(decl) => decl.copy_types(type_mapping),
-            TyNode::Expression(exp) => exp.copy_types(type_mapping),
-            TyNode::ReturnStatement(exp) => exp.copy_types(type_mapping),
+            TyNode::Declaration(decl) => decl.copy_types(session, type_mapping),
+            TyNode::Expression(exp) => exp.copy_types(session, type_mapping),
+            TyNode::ReturnStatement(exp) => exp.copy_types(session, type_mapping),
             TyNode::StarImport(_) => {}
         }
     }
@@ -6,7 +6,9 @@ use super::{typed_expression::*, TyNode};
 
 use crate::{
     collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
-    declaration_engine::declaration_id::DeclarationId,
+    declaration_engine::declaration_id::DeclId,
+    mangling::PathSegment,
+    session::Session,
     type_system::{
         type_engine::{insert_type, MonomorphizeHelper},
         type_id::TypeId,
@@ -20,10 +22,10 @@ use crate::{
 #[derive(Clone, PartialEq)]
 pub(crate) enum TyDeclaration {
     Variable(TyVariableDeclaration),
-    Function(CCIdx<DeclarationId>),
-    Trait(CCIdx<DeclarationId>),
-    TraitImpl(CCIdx<DeclarationId>),
-    Struct(CCIdx<DeclarationId>),
+    Function(CCIdx<DeclId<TyFunctionDeclaration>>),
+    Trait(CCIdx<DeclId<TyTraitDeclaration>>),
+    TraitImpl(CCIdx<DeclId<TyTraitImpl>>),
+    Struct(CCIdx<DeclId<TyStructDeclaration>>),
 }
 
 impl fmt::Display for TyDeclaration {
@@ -51,36 +53,26 @@ impl fmt::Debug for TyDeclaration {
 }
 
 impl CopyTypes for TyDeclaration {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         match self {
-            TyDeclaration::Variable(decl) => decl.copy_types(type_mapping),
-            TyDeclaration::Function(decl_id) => decl_id.copy_types(type_mapping),
-            TyDeclaration::Trait(decl_id) => decl_id.copy_types(type_mapping),
-            TyDeclaration::TraitImpl(decl_id) => decl_id.copy_types(type_mapping),
-            TyDeclaration::Struct(decl_id) => decl_id.copy_types(type_mapping),
+            TyDeclaration::Variable(decl) => decl.copy_types(session, type_mapping),
+            TyDeclaration::Function(decl_id) => decl_id.copy_types(session, type_mapping),
+            TyDeclaration::Trait(decl_id) => decl_id.copy_types(session, type_mapping),
+            TyDeclaration::TraitImpl(decl_id) => decl_id.copy_types(session, type_mapping),
+            TyDeclaration::Struct(decl_id) => decl_id.copy_types(session, type_mapping),
         }
     }
 }
 
-impl From<&TyFunctionParameter> for TyDeclaration {
+impl From<&TyFunctionParameter> for TyVariableDeclaration {
     fn from(param: &TyFunctionParameter) -> Self {
-        TyDeclaration::Variable(TyVariableDeclaration {
+        TyVariableDeclaration {
             name: param.name.clone(),
             type_ascription: param.type_id,
             body: TyExpression {
                 variant: TyExpressionVariant::FunctionParameter,
                 type_id: param.type_id,
             },
-        })
-    }
-}
-
-impl TyDeclaration {
-    pub(crate) fn expect_variable(self) -> Result<TyVariableDeclaration, String> {
-        if let TyDeclaration::Variable(variable_declaration) = self {
-            Ok(variable_declaration)
-        } else {
-            Err("not a variable declaration".to_string())
         }
     }
 }
@@ -113,9 +105,9 @@ impl fmt::Debug for TyVariableDeclaration {
 }
 
 impl CopyTypes for TyVariableDeclaration {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.type_ascription.copy_types(type_mapping);
-        self.body.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.type_ascription.copy_types(session, type_mapping);
+        self.body.copy_types(session, type_mapping);
     }
 }
 
@@ -129,15 +121,15 @@ pub(crate) struct TyFunctionDeclaration {
 }
 
 impl CopyTypes for TyFunctionDeclaration {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.type_parameters
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
+            .for_each(|x| x.copy_types(session, type_mapping));
         self.parameters
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
-        self.return_type.copy_types(type_mapping);
-        self.body.copy_types(type_mapping);
+            .for_each(|x| x.copy_types(session, type_mapping));
+        self.return_type.copy_types(session, type_mapping);
+        self.body.copy_types(session, type_mapping);
     }
 }
 
@@ -215,8 +207,8 @@ pub(crate) struct TyFunctionParameter {
 }
 
 impl CopyTypes for TyFunctionParameter {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.type_id.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.type_id.copy_types(session, type_mapping);
     }
 }
 
@@ -264,17 +256,17 @@ impl fmt::Display for TyCodeBlock {
 }
 
 impl CopyTypes for TyCodeBlock {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.contents
             .iter_mut()
-            .for_each(|node| node.copy_types(type_mapping));
+            .for_each(|node| node.copy_types(session, type_mapping));
     }
 }
 
 #[derive(Clone, PartialEq)]
 pub(crate) struct TyTraitDeclaration {
     pub(crate) name: String,
-    pub(crate) interface_surface: Vec<CCIdx<DeclarationId>>,
+    pub(crate) interface_surface: Vec<CCIdx<DeclId<TyTraitFn>>>,
 }
 
 impl fmt::Debug for TyTraitDeclaration {
@@ -304,10 +296,10 @@ impl fmt::Display for TyTraitDeclaration {
 }
 
 impl CopyTypes for TyTraitDeclaration {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.interface_surface
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
+            .for_each(|x| x.copy_types(session, type_mapping));
     }
 }
 
@@ -319,8 +311,8 @@ pub(crate) struct TyTraitFn {
 }
 
 impl CopyTypes for TyTraitFn {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.return_type.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.return_type.copy_types(session, type_mapping);
     }
 }
 
@@ -361,7 +353,7 @@ pub(crate) struct TyTraitImpl {
     pub(crate) trait_name: String,
     pub(crate) type_implementing_for: TypeId,
     pub(crate) type_parameters: Vec<TypeParameter>,
-    pub(crate) methods: Vec<CCIdx<DeclarationId>>,
+    pub(crate) methods: Vec<CCIdx<DeclId<TyFunctionDeclaration>>>,
 }
 
 impl fmt::Display for TyTraitImpl {
@@ -396,38 +388,47 @@ impl fmt::Display for TyTraitImpl {
 }
 
 impl CopyTypes for TyTraitImpl {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.methods
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
+            .for_each(|x| x.copy_types(session, type_mapping));
     }
 }
 
 #[derive(Clone, PartialEq)]
-pub(crate) struct TyStructDeclaration {
+pub struct TyStructDeclaration {
     pub(crate) name: String,
+    /// The name of the file that the struct is declared in.
+    pub(crate) file: String,
+    /// The path of the item within the file whose body the struct is declared
+    /// in, which is empty for a struct at the top level of the file.
+    pub(crate) path: Vec<PathSegment>,
     pub(crate) type_parameters: Vec<TypeParameter>,
     pub(crate) fields: Vec<TyStructField>,
 }
 
 impl CreateTypeId for TyStructDeclaration {
-    fn create_type_id(&self) -> TypeId {
-        insert_type(TypeInfo::Struct {
-            name: self.name.clone(),
-            type_parameters: self.type_parameters.clone(),
-            fields: self.fields.clone(),
-        })
+    fn create_type_id(&self, session: &Session, decl_id: DeclId<Self>) -> TypeId {
+        insert_type(
+            session,
+            TypeInfo::Struct {
+                decl_id,
+                name: self.name.clone(),
+                type_parameters: self.type_parameters.clone(),
+                fields: self.fields.clone(),
+            },
+        )
     }
 }
 
 impl CopyTypes for TyStructDeclaration {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         self.type_parameters
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
+            .for_each(|x| x.copy_types(session, type_mapping));
         self.fields
             .iter_mut()
-            .for_each(|x| x.copy_types(type_mapping));
+            .for_each(|x| x.copy_types(session, type_mapping));
     }
 }
 
@@ -522,8 +523,8 @@ pub struct TyStructField {
 }
 
 impl CopyTypes for TyStructField {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.type_id.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.type_id.copy_types(session, type_mapping);
     }
 }
 
@@ -4,6 +4,7 @@ use std::fmt::Write;
 
 use crate::{
     language::literal::Literal,
+    session::Session,
     type_system::{type_argument::TypeArgument, type_id::TypeId, type_mapping::TypeMapping},
     types::copy_types::CopyTypes,
 };
@@ -27,9 +28,9 @@ impl fmt::Debug for TyExpression {
 }
 
 impl CopyTypes for TyExpression {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.variant.copy_types(type_mapping);
-        self.type_id.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.variant.copy_types(session, type_mapping);
+        self.type_id.copy_types(session, type_mapping);
     }
 }
 
@@ -261,7 +262,7 @@ impl fmt::Debug for TyExpressionVariant {
 }
 
 impl CopyTypes for TyExpressionVariant {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         match self {
             TyExpressionVariant::FunctionApplication {
                 arguments,
@@ -270,10 +271,10 @@ impl CopyTypes for TyExpressionVariant {
             } => {
                 type_arguments
                     .iter_mut()
-                    .for_each(|type_arg| type_arg.copy_types(type_mapping));
+                    .for_each(|type_arg| type_arg.copy_types(session, type_mapping));
                 arguments
                     .iter_mut()
-                    .for_each(|argument| argument.copy_types(type_mapping));
+                    .for_each(|argument| argument.copy_types(session, type_mapping));
             }
             TyExpressionVariant::Struct {
                 fields,
@@ -282,10 +283,10 @@ impl CopyTypes for TyExpressionVariant {
             } => {
                 type_arguments
                     .iter_mut()
-                    .for_each(|type_arg| type_arg.copy_types(type_mapping));
+                    .for_each(|type_arg| type_arg.copy_types(session, type_mapping));
                 fields
                     .iter_mut()
-                    .for_each(|field| field.copy_types(type_mapping));
+                    .for_each(|field| field.copy_types(session, type_mapping));
             }
             TyExpressionVariant::MethodCall {
                 arguments,
@@ -294,10 +295,10 @@ impl CopyTypes for TyExpressionVariant {
             } => {
                 type_arguments
                     .iter_mut()
-                    .for_each(|type_arg| type_arg.copy_types(type_mapping));
+                    .for_each(|type_arg| type_arg.copy_types(session, type_mapping));
                 arguments
                     .iter_mut()
-                    .for_each(|argument| argument.copy_types(type_mapping));
+                    .for_each(|argument| argument.copy_types(session, type_mapping));
             }
             TyExpressionVariant::Literal { .. }
             | TyExpressionVariant::Variable { .. }
@@ -325,7 +326,7 @@ impl fmt::Debug for TyStructExpressionField {
 }
 
 impl CopyTypes for TyStructExpressionField {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.value.copy_types(type_mapping)
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.value.copy_types(session, type_mapping)
     }
 }
@@ -1,49 +1,56 @@
 use collection_context::collection_context::CollectionContext;
 use language::{parsed::Application, resolved::ResolvedApplication};
-use namespace::namespace::Namespace;
-use semantic_analysis::{
-    graph_collection::collect_graph, ty_to_resolved::to_resolved, type_collection::collect_types,
-    type_inference::analyze,
-};
+use query_engine::{query_cache::QueryCache, query_engine::QueryEngine};
+use semantic_analysis::graph_collection::collect_graph;
 
 mod collection_context;
 mod concurrent_slab;
 mod declaration_engine;
+mod incremental;
 pub mod language;
+mod mangling;
 mod namespace;
+mod query_engine;
 mod semantic_analysis;
+mod session;
 pub mod type_system;
 mod types;
+mod union_find;
 
-use declaration_engine::declaration_engine as de;
+pub use incremental::incremental_compiler::IncrementalCompiler;
+pub use mangling::demangle;
+pub use session::Session;
 
-#[allow(clippy::let_and_return)]
-pub fn compile(application: Application) -> ResolvedApplication {
-    de::de_clear();
+/// Compiles `application` in `session`, returning the first error found.
+pub fn compile(session: &Session, application: Application) -> Result<ResolvedApplication, String> {
+    session.enter(|| compile_in_session(session, application))
+}
 
+#[allow(clippy::let_and_return)]
+fn compile_in_session(
+    session: &Session,
+    application: Application,
+) -> Result<ResolvedApplication, String> {
     // 1. parsing happens here
 
     // 2. transform to the Ty AST and do graph collection
     let mut collection_context = CollectionContext::default();
-    let mut application = collect_graph(&mut collection_context, application);
+    let application = collect_graph(session, &mut collection_context, application)?;
 
     let cc_before_typing = collection_context.create_link();
 
-    // 3. do type collection
-    collect_types(&collection_context, &mut application);
-
-    // 4. do type inference with new namespace
-    let mut namespace = Namespace::default();
-    analyze(&collection_context, &mut namespace, &mut application);
+    // 3. query the resolved form of every root declaration, which does type
+    //    collection, type inference, and monomorphization on demand
+    let mut query_cache = QueryCache::default();
+    let resolved_application =
+        QueryEngine::new(session, &collection_context, &application, &mut query_cache)
+            .resolved_application()?;
 
     let cc_after_typing = collection_context.create_link();
 
     println!("\n\n!!! paste these links in your browser to see visualizations of the AST graphs !!!\n\nUNTYPED:\n\n{}\n\nTYPED:\n\n{}\n\n", cc_before_typing, cc_after_typing);
 
-    // 5. resolve all types
-    let resolved_application = to_resolved(&collection_context, application);
-
-    // 6. ir generation happens here
+    // 4. ir generation happens here
 
-    resolved_application
+    Ok(resolved_application)
 }
@@ -0,0 +1,264 @@
+//! Mangling of the paths of resolved declarations into symbol names.
+//!
+//! A symbol encodes the file that a declaration is defined in, the path of
+//! items to the declaration within that file, and the type arguments of every
+//! item on that path, so the instances of a generic declaration get distinct
+//! symbols. Symbols only depend on names and types, so they are the same from
+//! one compilation to the next. The grammar is:
+//!
+//! ```text
+//! symbol    := "_D" path
+//! path      := "N" segment+ "E"
+//! segment   := ident [ "I" type+ "E" ]       (an item and its type arguments)
+//!            | "X" type ident                (an impl of the trait ident)
+//! ident     := <decimal length> <bytes>
+//! type      := "h" | "t" | "j" | "m"         (u8, u16, u32, u64)
+//!            | "v"                           (the unit type)
+//!            | "S" path                      (a struct)
+//! ```
+//!
+//! [demangle] turns a symbol back into a readable path, such as
+//! `bob.sw::F<u32>` or `bob.sw::<Data as PingPong>::ping`.
+
+use crate::{
+    declaration_engine::declaration_engine::de_get_struct,
+    language::ty::typed_declaration::TyStructDeclaration,
+    session::Session,
+    type_system::{
+        type_engine::look_up_type_id, type_id::TypeId, type_info::TypeInfo,
+        type_parameter::TypeParameter, IntegerBits,
+    },
+};
+
+const SYMBOL_PREFIX: &str = "_D";
+
+/// One item on the path to a declaration.
+#[derive(Clone, PartialEq)]
+pub(crate) enum PathSegment {
+    Item {
+        name: String,
+        type_arguments: Vec<TypeId>,
+    },
+    Impl {
+        trait_name: String,
+        type_implementing_for: TypeId,
+    },
+}
+
+impl PathSegment {
+    /// A segment for the non-generic item `name`, like a file.
+    pub(crate) fn named(name: &str) -> PathSegment {
+        PathSegment::Item {
+            name: name.to_string(),
+            type_arguments: vec![],
+        }
+    }
+}
+
+/// The symbol of the declaration at `path`, which errors if a type on the path
+/// is not resolved.
+pub(crate) fn mangle(session: &Session, path: &[PathSegment]) -> Result<String, String> {
+    let mut symbol = SYMBOL_PREFIX.to_string();
+    mangle_path(session, path, &mut symbol)?;
+    Ok(symbol)
+}
+
+/// The symbol of the struct declaration `struct_decl`.
+pub(crate) fn mangle_struct(
+    session: &Session,
+    struct_decl: &TyStructDeclaration,
+) -> Result<String, String> {
+    mangle(
+        session,
+        &struct_path(struct_decl, &struct_decl.type_parameters),
+    )
+}
+
+/// Structs are identified by their file, the path of the item whose body they
+/// are declared in, and their name, so structs of the same name that are
+/// declared in different functions get different symbols.
+fn struct_path(
+    struct_decl: &TyStructDeclaration,
+    type_parameters: &[TypeParameter],
+) -> Vec<PathSegment> {
+    let mut path = vec![PathSegment::named(&struct_decl.file)];
+    path.extend(struct_decl.path.iter().cloned());
+    path.push(PathSegment::Item {
+        name: struct_decl.name.clone(),
+        type_arguments: type_parameters
+            .iter()
+            .map(|type_parameter| type_parameter.type_id)
+            .collect(),
+    });
+    path
+}
+
+fn mangle_path(session: &Session, path: &[PathSegment], out: &mut String) -> Result<(), String> {
+    out.push('N');
+    for segment in path.iter() {
+        match segment {
+            PathSegment::Item {
+                name,
+                type_arguments,
+            } => {
+                mangle_ident(name, out);
+                if !type_arguments.is_empty() {
+                    out.push('I');
+                    for type_argument in type_arguments.iter() {
+                        mangle_type(session, *type_argument, out)?;
+                    }
+                    out.push('E');
+                }
+            }
+            PathSegment::Impl {
+                trait_name,
+                type_implementing_for,
+            } => {
+                out.push('X');
+                mangle_type(session, *type_implementing_for, out)?;
+                mangle_ident(trait_name, out);
+            }
+        }
+    }
+    out.push('E');
+    Ok(())
+}
+
+fn mangle_ident(ident: &str, out: &mut String) {
+    out.push_str(&ident.len().to_string());
+    out.push_str(ident);
+}
+
+fn mangle_type(session: &Session, type_id: TypeId, out: &mut String) -> Result<(), String> {
+    match &*look_up_type_id(session, type_id)? {
+        TypeInfo::UnsignedInteger(IntegerBits::Eight) => out.push('h'),
+        TypeInfo::UnsignedInteger(IntegerBits::Sixteen) => out.push('t'),
+        TypeInfo::UnsignedInteger(IntegerBits::ThirtyTwo) => out.push('j'),
+        TypeInfo::UnsignedInteger(IntegerBits::SixtyFour) => out.push('m'),
+        TypeInfo::Unit => out.push('v'),
+        TypeInfo::Struct {
+            decl_id,
+            type_parameters,
+            ..
+        } => {
+            let struct_decl = de_get_struct(session, *decl_id)?;
+            out.push('S');
+            mangle_path(session, &struct_path(&struct_decl, type_parameters), out)?;
+        }
+        found @ TypeInfo::ErrorRecovery
+        | found @ TypeInfo::Unknown
+        | found @ TypeInfo::UnknownGeneric { .. }
+        | found @ TypeInfo::Custom { .. } => {
+            return Err(format!("type error in mangling, found: {:?}", found));
+        }
+    }
+    Ok(())
+}
+
+/// Turns a symbol made by mangling back into the readable path of the
+/// declaration.
+pub fn demangle(symbol: &str) -> Result<String, String> {
+    let mangled = symbol
+        .strip_prefix(SYMBOL_PREFIX)
+        .ok_or_else(|| format!("not a mangled symbol: {}", symbol))?;
+    let mut demangler = Demangler {
+        symbol,
+        rest: mangled,
+    };
+    let path = demangler.path()?;
+    if !demangler.rest.is_empty() {
+        return Err(demangler.error());
+    }
+    Ok(path)
+}
+
+struct Demangler<'a> {
+    symbol: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Demangler<'a> {
+    fn error(&self) -> String {
+        format!("invalid mangled symbol: {}", self.symbol)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn path(&mut self) -> Result<String, String> {
+        self.expect('N')?;
+        let mut segments = vec![];
+        while !self.eat('E') {
+            segments.push(self.segment()?);
+        }
+        if segments.is_empty() {
+            return Err(self.error());
+        }
+        Ok(segments.join("::"))
+    }
+
+    fn segment(&mut self) -> Result<String, String> {
+        if self.eat('X') {
+            let type_implementing_for = self.type_()?;
+            let trait_name = self.ident()?;
+            return Ok(format!("<{} as {}>", type_implementing_for, trait_name));
+        }
+        let name = self.ident()?;
+        if !self.eat('I') {
+            return Ok(name.to_string());
+        }
+        let mut type_arguments = vec![];
+        while !self.eat('E') {
+            type_arguments.push(self.type_()?);
+        }
+        if type_arguments.is_empty() {
+            return Err(self.error());
+        }
+        Ok(format!("{}<{}>", name, type_arguments.join(", ")))
+    }
+
+    fn ident(&mut self) -> Result<&'a str, String> {
+        let digits = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let len: usize = self.rest[..digits].parse().map_err(|_| self.error())?;
+        let rest = &self.rest[digits..];
+        if rest.len() < len || !rest.is_char_boundary(len) {
+            return Err(self.error());
+        }
+        let (ident, rest) = rest.split_at(len);
+        self.rest = rest;
+        Ok(ident)
+    }
+
+    fn type_(&mut self) -> Result<String, String> {
+        let mut chars = self.rest.chars();
+        let c = chars.next().ok_or_else(|| self.error())?;
+        self.rest = chars.as_str();
+        match c {
+            'h' => Ok(IntegerBits::Eight.to_string()),
+            't' => Ok(IntegerBits::Sixteen.to_string()),
+            'j' => Ok(IntegerBits::ThirtyTwo.to_string()),
+            'm' => Ok(IntegerBits::SixtyFour.to_string()),
+            'v' => Ok("()".to_string()),
+            'S' => self.path(),
+            _ => Err(self.error()),
+        }
+    }
+}
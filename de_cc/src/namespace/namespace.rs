@@ -4,35 +4,28 @@ use std::fmt::Write;
 
 use crate::{
     collection_context::collection_index::CCIdx,
-    declaration_engine::{
-        declaration_engine::*, declaration_id::DeclarationId,
-        declaration_wrapper::DeclarationWrapper,
+    declaration_engine::{declaration_engine::*, declaration_id::DeclId},
+    language::ty::typed_declaration::TyTraitFn,
+    session::Session,
+    type_system::{
+        type_engine::are_equivalent_types, type_id::TypeId, type_parameter::TypeParameter,
     },
-    language::ty::typed_declaration::TyDeclaration,
-    type_system::{type_engine::look_up_type_id, type_id::TypeId},
 };
-use linked_hash_map::LinkedHashMap;
 
 use super::function_signature::TypedFunctionSignature;
 
-type MethodList = Vec<CCIdx<DeclarationId>>;
+type MethodList = Vec<CCIdx<DeclId<TyTraitFn>>>;
 
 #[derive(Default)]
 pub(crate) struct Namespace {
-    symbols: LinkedHashMap<String, TyDeclaration>,
     // this should be (type info, trait name) -> declaration id
     methods: Vec<(TypeId, MethodList)>,
+    /// The type parameters of the function whose body is being analyzed.
+    type_parameters: Vec<TypeParameter>,
 }
 
 impl fmt::Display for Namespace {
     fn fmt(&self, mut f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f).unwrap();
-        {
-            let mut indent = IndentWriter::new("  ", &mut f);
-            for symbol in self.symbols.keys() {
-                writeln!(indent, "{}", symbol).unwrap();
-            }
-        }
         writeln!(f).unwrap();
         {
             let mut indent = IndentWriter::new("  ", &mut f);
@@ -45,63 +38,49 @@ impl fmt::Display for Namespace {
 }
 
 impl Namespace {
-    pub(crate) fn scoped(&self) -> Namespace {
-        Namespace {
-            symbols: self.symbols.clone(),
-            methods: self.methods.clone(),
-        }
-    }
-
     #[allow(dead_code)]
     pub fn debug_print(&self) {
         println!("\n\n~~~~~~~~~~\n\nNamespace:\n{}\n\n~~~~~~~~~~", self);
     }
 
-    pub(crate) fn insert_symbol(&mut self, name: String, symbol: TyDeclaration) {
-        self.symbols.insert(name, symbol);
+    pub(crate) fn insert_type_parameters(&mut self, type_parameters: &[TypeParameter]) {
+        self.type_parameters.extend_from_slice(type_parameters);
     }
 
-    pub(crate) fn get_symbol(&self, name: &str) -> Result<TyDeclaration, String> {
-        self.symbols
-            .get(name)
-            .cloned()
-            .ok_or_else(|| "symbol not found".to_string())
+    /// The type parameters that the names of type parameters in types refer to.
+    pub(crate) fn type_parameters(&self) -> &[TypeParameter] {
+        &self.type_parameters
     }
 
     pub(crate) fn insert_methods(
         &mut self,
+        session: &Session,
         type_id: TypeId,
         _trait_name: String,
-        mut methods: Vec<CCIdx<DeclarationId>>,
-    ) {
+        mut methods: MethodList,
+    ) -> Result<(), String> {
         for (k, v) in self.methods.iter_mut() {
-            // TODO: consider semantic similarity
-            if look_up_type_id(*k) == look_up_type_id(type_id) {
+            if are_equivalent_types(session, *k, type_id)? {
                 v.append(&mut methods);
-                return;
+                return Ok(());
             }
         }
         self.methods.push((type_id, methods));
+        Ok(())
     }
 
     pub(crate) fn get_method(
         &self,
+        session: &Session,
         type_id: TypeId,
         func_name: &str,
     ) -> Result<TypedFunctionSignature, String> {
         for (k, method_ids) in self.methods.iter() {
-            // TODO: consider semantic similarity
-            if look_up_type_id(*k) == look_up_type_id(type_id) {
+            if are_equivalent_types(session, *k, type_id)? {
                 for method_id in method_ids.iter() {
-                    let (name, signature) = match de_look_up_decl_id(*method_id.inner_ref()) {
-                        DeclarationWrapper::Function(decl) => (decl.name.clone(), decl.into()),
-                        DeclarationWrapper::TraitFn(decl) => (decl.name.clone(), decl.into()),
-                        _ => {
-                            return Err("found bad item in self.methods".to_string());
-                        }
-                    };
-                    if name == func_name {
-                        return Ok(signature);
+                    let trait_fn = de_get_trait_fn(session, *method_id.inner_ref())?;
+                    if trait_fn.name == func_name {
+                        return Ok((&*trait_fn).into());
                     }
                 }
             }
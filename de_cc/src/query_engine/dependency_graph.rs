@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use petgraph::{algo::tarjan_scc, Graph};
+
+use crate::{
+    collection_context::{
+        collection_context::CollectionContext,
+        collection_index::{CCIdx, CollectionIndex},
+    },
+    declaration_engine::{declaration_engine::*, declaration_id::DeclId},
+    language::ty::{
+        typed_declaration::{TyDeclaration, TyFunctionDeclaration},
+        typed_expression::{TyExpression, TyExpressionVariant},
+        TyFile, TyNode,
+    },
+    session::Session,
+    type_system::{type_engine::look_up_type_id, type_id::TypeId, type_info::TypeInfo},
+};
+
+/// Something that a file-level node refers to, which may be declared in
+/// another file-level node.
+enum Use {
+    /// A symbol that is looked up from the given index---a called function,
+    /// a struct, a trait, etc.
+    Symbol(CollectionIndex, String),
+    /// A method that is called on some value. Because the type of the value
+    /// is not known yet, this refers to every trait impl with a method of
+    /// this name.
+    Method(String),
+}
+
+/// Orders the file-level nodes of `files` for type inference, which is done by
+/// [QueryEngine::type_check_application](super::query_engine::QueryEngine::type_check_application).
+///
+/// A dependency graph is built between the file-level nodes, where an edge
+/// points from a node to every node that it uses---through function calls,
+/// struct expressions, types, trait constraints, and trait impls. The strongly
+/// connected components of this graph are returned in topological order, so
+/// that every component comes after the components that it depends on. Nodes
+/// are identified by their position in `files` when flattened, and the nodes
+/// within a component are kept in source order.
+pub(super) fn dependency_order(
+    session: &Session,
+    cc: &CollectionContext,
+    files: &[CCIdx<TyFile>],
+) -> Vec<Vec<usize>> {
+    let nodes = files
+        .iter()
+        .flat_map(|file| file.inner_ref().nodes.iter())
+        .collect::<Vec<_>>();
+
+    let mut collector = DependencyCollector {
+        session,
+        current: 0,
+        owners: HashMap::new(),
+        methods: HashMap::new(),
+        uses: vec![],
+    };
+    for (i, node) in nodes.iter().enumerate() {
+        collector.current = i;
+        collector.uses.push(vec![]);
+        collector.collect_node(node);
+    }
+
+    let mut graph = Graph::<usize, ()>::new();
+    let graph_nodes = (0..nodes.len())
+        .map(|i| graph.add_node(i))
+        .collect::<Vec<_>>();
+    for (i, uses) in collector.uses.iter().enumerate() {
+        for r#use in uses.iter() {
+            for dependency in collector.resolve_use(cc, r#use) {
+                graph.update_edge(graph_nodes[i], graph_nodes[dependency], ());
+            }
+        }
+    }
+
+    // tarjan_scc returns the components in reverse topological order, which
+    // puts every dependency before the nodes that depend on it
+    tarjan_scc(&graph)
+        .into_iter()
+        .map(|component| {
+            let mut component = component
+                .into_iter()
+                .map(|node| graph[node])
+                .collect::<Vec<_>>();
+            component.sort_unstable();
+            component
+        })
+        .collect()
+}
+
+struct DependencyCollector<'s> {
+    session: &'s Session,
+    /// The file-level node currently being visited.
+    current: usize,
+    /// The file-level node that every visited declaration belongs to.
+    owners: HashMap<CollectionIndex, usize>,
+    /// The file-level trait impls that provide a method of a given name.
+    methods: HashMap<String, Vec<usize>>,
+    /// The uses of every file-level node.
+    uses: Vec<Vec<Use>>,
+}
+
+impl DependencyCollector<'_> {
+    fn resolve_use(&self, cc: &CollectionContext, r#use: &Use) -> Vec<usize> {
+        match r#use {
+            Use::Symbol(index, name) => cc
+                .get_symbol(*index, name)
+                .ok()
+                .and_then(|decl_id| self.owners.get(&decl_id.idx()).cloned())
+                .into_iter()
+                .collect(),
+            Use::Method(name) => self.methods.get(name).cloned().unwrap_or_default(),
+        }
+    }
+
+    fn add_use(&mut self, r#use: Use) {
+        self.uses[self.current].push(r#use);
+    }
+
+    fn collect_node(&mut self, node: &CCIdx<TyNode>) {
+        match node.inner_ref() {
+            TyNode::StarImport(_) => {}
+            TyNode::Declaration(decl) => self.collect_declaration(decl),
+            TyNode::Expression(exp) => self.collect_expression(node.idx(), exp),
+            TyNode::ReturnStatement(exp) => self.collect_expression(node.idx(), exp),
+        }
+    }
+
+    fn collect_declaration(&mut self, decl: &CCIdx<TyDeclaration>) {
+        let index = decl.idx();
+        self.owners.insert(index, self.current);
+        match decl.inner_ref() {
+            TyDeclaration::Variable(var_decl) => {
+                self.collect_type(index, var_decl.type_ascription);
+                self.collect_expression(index, &var_decl.body);
+            }
+            TyDeclaration::Function(decl_id) => self.collect_function(decl_id),
+            TyDeclaration::Trait(decl_id) => {
+                let trait_decl = de_get_trait(self.session, *decl_id.inner_ref()).unwrap();
+                for trait_fn_id in trait_decl.interface_surface.iter() {
+                    self.owners.insert(trait_fn_id.idx(), self.current);
+                    let trait_fn = de_get_trait_fn(self.session, *trait_fn_id.inner_ref()).unwrap();
+                    for param in trait_fn.parameters.iter() {
+                        self.collect_type(trait_fn_id.idx(), param.type_id);
+                    }
+                    self.collect_type(trait_fn_id.idx(), trait_fn.return_type);
+                }
+            }
+            TyDeclaration::TraitImpl(decl_id) => {
+                let trait_impl = de_get_trait_impl(self.session, *decl_id.inner_ref()).unwrap();
+                self.add_use(Use::Symbol(index, trait_impl.trait_name.clone()));
+                self.collect_type(index, trait_impl.type_implementing_for);
+                for method_id in trait_impl.methods.iter() {
+                    let method = de_get_function(self.session, *method_id.inner_ref()).unwrap();
+                    self.methods
+                        .entry(method.name.clone())
+                        .or_default()
+                        .push(self.current);
+                    self.collect_function(method_id);
+                }
+            }
+            TyDeclaration::Struct(decl_id) => {
+                let struct_decl = de_get_struct(self.session, *decl_id.inner_ref()).unwrap();
+                for field in struct_decl.fields.iter() {
+                    self.collect_type(index, field.type_id);
+                }
+            }
+        }
+    }
+
+    fn collect_function(&mut self, decl_id: &CCIdx<DeclId<TyFunctionDeclaration>>) {
+        let index = decl_id.idx();
+        self.owners.insert(index, self.current);
+        let func_decl = de_get_function(self.session, *decl_id.inner_ref()).unwrap();
+        for type_param in func_decl.type_parameters.iter() {
+            if let Some(constraint) = &type_param.trait_constraint {
+                self.add_use(Use::Symbol(index, constraint.trait_name.clone()));
+            }
+        }
+        for param in func_decl.parameters.iter() {
+            self.collect_type(index, param.type_id);
+        }
+        self.collect_type(index, func_decl.return_type);
+        for node in func_decl.body.inner_ref().contents.iter() {
+            self.collect_node(node);
+        }
+    }
+
+    fn collect_expression(&mut self, index: CollectionIndex, exp: &TyExpression) {
+        match &exp.variant {
+            TyExpressionVariant::Literal { .. }
+            | TyExpressionVariant::Variable { .. }
+            | TyExpressionVariant::FunctionParameter => {}
+            TyExpressionVariant::FunctionApplication {
+                name, arguments, ..
+            } => {
+                self.add_use(Use::Symbol(index, name.clone()));
+                for argument in arguments.iter() {
+                    self.collect_expression(index, argument);
+                }
+            }
+            TyExpressionVariant::Struct {
+                struct_name,
+                fields,
+                ..
+            } => {
+                self.add_use(Use::Symbol(index, struct_name.clone()));
+                for field in fields.iter() {
+                    self.collect_expression(index, &field.value);
+                }
+            }
+            TyExpressionVariant::MethodCall {
+                func_name,
+                arguments,
+                ..
+            } => {
+                self.add_use(Use::Method(func_name.clone()));
+                for argument in arguments.iter() {
+                    self.collect_expression(index, argument);
+                }
+            }
+        }
+    }
+
+    fn collect_type(&mut self, index: CollectionIndex, type_id: TypeId) {
+        self.collect_type_info(index, &look_up_type_id(self.session, type_id).unwrap());
+    }
+
+    fn collect_type_info(&mut self, index: CollectionIndex, type_info: &TypeInfo) {
+        match type_info {
+            TypeInfo::Custom {
+                name,
+                type_arguments,
+            } => {
+                self.add_use(Use::Symbol(index, name.clone()));
+                for type_argument in type_arguments.iter() {
+                    self.collect_type_info(index, type_argument);
+                }
+            }
+            TypeInfo::Struct {
+                name,
+                type_parameters,
+                ..
+            } => {
+                self.add_use(Use::Symbol(index, name.clone()));
+                for type_parameter in type_parameters.iter() {
+                    self.collect_type(index, type_parameter.type_id);
+                }
+            }
+            TypeInfo::ErrorRecovery
+            | TypeInfo::Unknown
+            | TypeInfo::UnknownGeneric { .. }
+            | TypeInfo::Unit
+            | TypeInfo::UnsignedInteger(_) => {}
+        }
+    }
+}
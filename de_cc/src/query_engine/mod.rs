@@ -0,0 +1,5 @@
+mod dependency_graph;
+pub(crate) mod query_cache;
+#[allow(clippy::module_inception)]
+pub(crate) mod query_engine;
+pub(crate) mod query_key;
@@ -0,0 +1,47 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection_context::collection_index::{CCIdx, CollectionIndex},
+    declaration_engine::declaration_id::DeclId,
+    language::ty::typed_declaration::TyFunctionDeclaration,
+    namespace::function_signature::TypedFunctionSignature,
+    type_system::type_id::TypeId,
+};
+
+/// The memoized results of the queries made to a
+/// [QueryEngine](super::query_engine::QueryEngine).
+///
+/// Results are keyed by the [CollectionIndex] of the node that they were
+/// computed for, so a [QueryCache] can outlive the [QueryEngine](super::query_engine::QueryEngine)
+/// that filled it, and be reused for as long as those nodes stay in the
+/// [CollectionContext](crate::collection_context::collection_context::CollectionContext).
+#[derive(Default)]
+pub(crate) struct QueryCache {
+    pub(super) types: HashMap<CollectionIndex, TypeId>,
+    pub(super) signatures: HashMap<CollectionIndex, TypedFunctionSignature>,
+    pub(super) impl_headers: HashMap<CollectionIndex, TypeId>,
+    pub(super) impls: HashMap<TypeId, Vec<CCIdx<DeclId<TyFunctionDeclaration>>>>,
+    pub(super) monomorphized:
+        HashMap<(CollectionIndex, Vec<TypeId>), DeclId<TyFunctionDeclaration>>,
+}
+
+impl QueryCache {
+    /// Forgets the methods found for every type. Unlike the other results,
+    /// these depend on every trait impl in the application, so they must be
+    /// recomputed whenever a trait impl may have been added or removed.
+    pub(crate) fn forget_impls(&mut self) {
+        self.impls.clear();
+    }
+
+    /// Forgets the results computed for the nodes at `indices`, which are gone
+    /// from the collection context, so that the nodes added at the same
+    /// indices later do not see them.
+    pub(crate) fn forget(&mut self, indices: &HashSet<CollectionIndex>) {
+        self.types.retain(|index, _| !indices.contains(index));
+        self.signatures.retain(|index, _| !indices.contains(index));
+        self.impl_headers
+            .retain(|index, _| !indices.contains(index));
+        self.monomorphized
+            .retain(|(index, _), _| !indices.contains(index));
+    }
+}
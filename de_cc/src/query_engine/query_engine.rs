@@ -0,0 +1,505 @@
+use crate::{
+    collection_context::{
+        collection_context::CollectionContext,
+        collection_index::{CCIdx, CollectionIndex},
+        collection_node::CollectionNode,
+    },
+    declaration_engine::{
+        declaration_engine::*,
+        declaration_id::{DeclId, DeclarationId},
+        declaration_wrapper::DeclarationWrapper,
+    },
+    language::{
+        resolved::{ResolvedApplication, ResolvedFile},
+        ty::{
+            typed_declaration::{TyDeclaration, TyFunctionDeclaration, TyTraitImpl},
+            typed_expression::TyExpression,
+            TyApplication, TyNode,
+        },
+    },
+    mangling::PathSegment,
+    namespace::{function_signature::TypedFunctionSignature, namespace::Namespace},
+    semantic_analysis::{
+        instance_collection::*, ty_to_resolved::*, type_collection::*, type_inference::*,
+    },
+    session::Session,
+    type_system::{
+        type_argument::TypeArgument,
+        type_engine::{canonical_type_id, insert_type, monomorphize},
+        type_id::TypeId,
+        type_info::TypeInfo,
+    },
+    types::create_type_id::CreateTypeId,
+};
+
+use super::{dependency_graph::dependency_order, query_cache::QueryCache, query_key::QueryKey};
+
+/// Drives type collection, type inference, and monomorphization on demand.
+///
+/// Rather than running each of these as a phase over the entire application,
+/// every piece of information is computed by a query the first time that it is
+/// asked for and memoized by the key of that query. Queries may make other
+/// queries---inferring the body of a function asks for the signatures of the
+/// functions that it calls, for instance---and a query that ends up asking for
+/// itself is reported as a cycle.
+pub(crate) struct QueryEngine<'cc> {
+    session: &'cc Session,
+    cc: &'cc CollectionContext,
+    application: &'cc CCIdx<TyApplication>,
+    /// Every trait impl in the application.
+    trait_impls: Vec<CCIdx<DeclId<TyTraitImpl>>>,
+    /// The queries currently being computed, innermost last.
+    active: Vec<QueryKey>,
+    cache: &'cc mut QueryCache,
+}
+
+impl<'cc> QueryEngine<'cc> {
+    pub(crate) fn new(
+        session: &'cc Session,
+        cc: &'cc CollectionContext,
+        application: &'cc CCIdx<TyApplication>,
+        cache: &'cc mut QueryCache,
+    ) -> QueryEngine<'cc> {
+        let mut trait_impls = vec![];
+        for file in application.inner_ref().files.iter() {
+            find_trait_impls(session, &file.inner_ref().nodes, &mut trait_impls);
+        }
+
+        // the trait impls of this application replace those of an application
+        // compiled before in the same session. An impl of a trait that does not
+        // resolve is left out, and reported by its type collection
+        de_set_trait_impls(
+            session,
+            trait_impls
+                .iter()
+                .filter_map(|impl_id| {
+                    let trait_impl = de_get_trait_impl(session, *impl_id.inner_ref()).ok()?;
+                    let trait_id = cc
+                        .get_symbol(impl_id.idx(), &trait_impl.trait_name)
+                        .and_then(|trait_id| trait_id.expect(session))
+                        .ok()?;
+                    Some((trait_id.inner(), *impl_id.inner_ref()))
+                })
+                .collect(),
+        );
+
+        QueryEngine {
+            session,
+            cc,
+            application,
+            trait_impls,
+            active: vec![],
+            cache,
+        }
+    }
+
+    pub(crate) fn session(&self) -> &'cc Session {
+        self.session
+    }
+
+    pub(crate) fn cc(&self) -> &'cc CollectionContext {
+        self.cc
+    }
+
+    pub(crate) fn application(&self) -> &'cc CCIdx<TyApplication> {
+        self.application
+    }
+
+    /// Resolves every file-level node of the application.
+    ///
+    /// Generic declarations resolve to one declaration per instance that is
+    /// reachable from the entry points of the application, so the application
+    /// is type checked and the instances are collected before resolving any
+    /// node.
+    pub(crate) fn resolved_application(&mut self) -> Result<ResolvedApplication, String> {
+        self.type_check_application()?;
+        let instances = collect_instances(self)?;
+        let application = self.application;
+        let mut files = vec![];
+        for file in application.inner_ref().files.iter() {
+            let mut nodes = vec![];
+            for node in file.inner_ref().nodes.iter() {
+                nodes.append(&mut to_resolved_node(
+                    self.session,
+                    self.cc,
+                    &instances,
+                    &[PathSegment::named(&file.inner_ref().name)],
+                    node.clone(),
+                )?);
+            }
+            files.push(ResolvedFile {
+                name: file.inner_ref().name.clone(),
+                nodes,
+            });
+        }
+        Ok(ResolvedApplication { files })
+    }
+
+    /// Type checks every file-level node of the application.
+    ///
+    /// The nodes are visited by the strongly connected components of their
+    /// dependency graph in topological order, so every node is inferred after
+    /// the nodes that it depends on, and mutually recursive nodes are inferred
+    /// together. The queries of a node still ask for anything that they need
+    /// on demand, so this only decides which query is asked first.
+    pub(crate) fn type_check_application(&mut self) -> Result<(), String> {
+        let files = &self.application.inner_ref().files;
+        let nodes = files
+            .iter()
+            .flat_map(|file| file.inner_ref().nodes.iter())
+            .collect::<Vec<_>>();
+        for component in dependency_order(self.session, self.cc, files).into_iter() {
+            for node in component.into_iter() {
+                self.type_check_node(nodes[node])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The type of `decl`, after doing type inference on it. For functions this
+    /// is the return type and for trait impls this is the type implemented for.
+    ///
+    /// Every declaration is inferred in an empty [Namespace]. Variables are
+    /// looked up through the scopes of the
+    /// [CollectionContext](crate::collection_context::collection_context::CollectionContext)
+    /// rather than the namespace, and the only thing that the namespace holds is
+    /// the methods of the trait constraints of the function being inferred,
+    /// which a function adds itself and which do not apply to the declarations
+    /// nested in it.
+    pub(crate) fn type_of(&mut self, decl: &CCIdx<TyDeclaration>) -> Result<TypeId, String> {
+        self.memoized_type_of(decl.idx(), |qe| match decl.inner_ref() {
+            TyDeclaration::Variable(var_decl) => {
+                analyze_variable(qe, decl.idx(), &mut Namespace::default(), var_decl)?;
+                Ok(var_decl.type_ascription)
+            }
+            TyDeclaration::Function(decl_id) => {
+                qe.signature_of(&decl_id.untyped())?;
+                analyze_function(qe, &mut Namespace::default(), decl_id)?;
+                Ok(de_get_function(self.session, *decl_id.inner_ref())?.return_type)
+            }
+            TyDeclaration::Trait(decl_id) => {
+                for trait_fn_id in de_get_trait(self.session, *decl_id.inner_ref())?
+                    .interface_surface
+                    .iter()
+                {
+                    qe.signature_of(&trait_fn_id.untyped())?;
+                }
+                Ok(insert_type(self.session, TypeInfo::Unit))
+            }
+            TyDeclaration::TraitImpl(decl_id) => {
+                let type_implementing_for = qe.impl_header_of(decl_id)?;
+                for method_id in de_get_trait_impl(self.session, *decl_id.inner_ref())?
+                    .methods
+                    .iter()
+                {
+                    qe.type_of(&CCIdx::new(
+                        TyDeclaration::Function(method_id.clone()),
+                        method_id.idx(),
+                    ))?;
+                }
+                Ok(type_implementing_for)
+            }
+            TyDeclaration::Struct(decl_id) => {
+                collect_types_struct(qe.session, qe.cc, decl_id)?;
+                Ok(de_get_struct(self.session, *decl_id.inner_ref())?
+                    .create_type_id(self.session, *decl_id.inner_ref()))
+            }
+        })
+    }
+
+    /// The same as [QueryEngine::type_of], for a declaration that is only known
+    /// by its [DeclarationId].
+    pub(crate) fn type_of_decl_id(
+        &mut self,
+        decl_id: &CCIdx<DeclarationId>,
+    ) -> Result<TypeId, String> {
+        let decl = match &*de_look_up_decl_id(self.session, *decl_id.inner_ref())? {
+            DeclarationWrapper::Function(_) => {
+                TyDeclaration::Function(decl_id.expect(self.session)?)
+            }
+            DeclarationWrapper::Trait(_) => TyDeclaration::Trait(decl_id.expect(self.session)?),
+            DeclarationWrapper::TraitImpl(_) => {
+                TyDeclaration::TraitImpl(decl_id.expect(self.session)?)
+            }
+            DeclarationWrapper::Struct(_) => TyDeclaration::Struct(decl_id.expect(self.session)?),
+            got => return Err(format!("err, found: {}", got)),
+        };
+        self.type_of(&CCIdx::new(decl, decl_id.idx()))
+    }
+
+    /// The signature of the function or trait fn `func`, after doing type
+    /// collection on it.
+    pub(crate) fn signature_of(
+        &mut self,
+        func: &CCIdx<DeclarationId>,
+    ) -> Result<TypedFunctionSignature, String> {
+        if let Some(signature) = self.cache.signatures.get(&func.idx()) {
+            return Ok(signature.clone());
+        }
+        self.enter(QueryKey::SignatureOf(func.idx()))?;
+        let signature = match &*de_look_up_decl_id(self.session, *func.inner_ref())? {
+            DeclarationWrapper::Function(_) => func.expect(self.session).and_then(|func| {
+                collect_types_function(self.session, self.cc, &func)?;
+                de_get_function(self.session, *func.inner_ref())
+                    .map(|func_decl| TypedFunctionSignature::from(&*func_decl))
+            }),
+            DeclarationWrapper::TraitFn(_) => func.expect(self.session).and_then(|func| {
+                collect_types_trait_fn(self.session, self.cc, &func)?;
+                de_get_trait_fn(self.session, *func.inner_ref())
+                    .map(|trait_fn| TypedFunctionSignature::from(&*trait_fn))
+            }),
+            got => Err(format!("err, found: {}", got)),
+        };
+        self.exit();
+        let signature = signature?;
+        self.cache.signatures.insert(func.idx(), signature.clone());
+        Ok(signature)
+    }
+
+    /// The methods implemented for `type_id` across every trait impl in the
+    /// application.
+    ///
+    /// The methods are memoized by the interned id of the type, so only the
+    /// methods of concrete types are memoized, and the methods of any other
+    /// type are found again every time they are asked for.
+    pub(crate) fn impls_for(
+        &mut self,
+        type_id: TypeId,
+    ) -> Result<Vec<CCIdx<DeclId<TyFunctionDeclaration>>>, String> {
+        let canonical_id = canonical_type_id(self.session, type_id)?;
+        if let Some(methods) = canonical_id.and_then(|key| self.cache.impls.get(&key)) {
+            return Ok(methods.clone());
+        }
+        self.enter(QueryKey::ImplsFor(canonical_id.unwrap_or(type_id)))?;
+        let methods = self.find_impls(type_id);
+        self.exit();
+        let methods = methods?;
+        if let Some(canonical_id) = canonical_id {
+            self.cache.impls.insert(canonical_id, methods.clone());
+        }
+        Ok(methods)
+    }
+
+    /// Finds the signature of the method `method_name` implemented for `type_id`.
+    pub(crate) fn method_of(
+        &mut self,
+        type_id: TypeId,
+        method_name: &str,
+    ) -> Result<TypedFunctionSignature, String> {
+        let method_id = self.method_id_of(type_id, method_name)?;
+        self.signature_of(&method_id.untyped())
+    }
+
+    /// Finds the method `method_name` implemented for `type_id`.
+    pub(crate) fn method_id_of(
+        &mut self,
+        type_id: TypeId,
+        method_name: &str,
+    ) -> Result<CCIdx<DeclId<TyFunctionDeclaration>>, String> {
+        for method_id in self.impls_for(type_id)?.into_iter() {
+            if self.signature_of(&method_id.untyped())?.name == method_name {
+                return Ok(method_id);
+            }
+        }
+        Err("could not find function".to_string())
+    }
+
+    /// Creates a copy of `func` with its type parameters replaced by
+    /// `type_arguments`, adds it to the declaration engine as requested by the
+    /// node `requested_by`, and returns the id of the copy.
+    ///
+    /// Because the copy includes the function body, the body of a generic
+    /// function is inferred before it is copied. The only exception is when the
+    /// body is already being inferred further up the query stack, which happens
+    /// for (mutually) recursive functions. In that case the copy is made from
+    /// the partially inferred function.
+    pub(crate) fn monomorphize(
+        &mut self,
+        func: &CCIdx<DeclId<TyFunctionDeclaration>>,
+        type_arguments: &[TypeArgument],
+        requested_by: CollectionIndex,
+    ) -> Result<DeclId<TyFunctionDeclaration>, String> {
+        let key = (
+            func.idx(),
+            type_arguments
+                .iter()
+                .map(|type_argument| type_argument.type_id)
+                .collect::<Vec<_>>(),
+        );
+        if let Some(copy) = self.cache.monomorphized.get(&key) {
+            return Ok(*copy);
+        }
+        self.enter(QueryKey::Monomorphize(key.0, key.1.clone()))?;
+        let copy = self.compute_monomorphize(func, type_arguments, requested_by);
+        self.exit();
+        let copy = copy?;
+        self.cache.monomorphized.insert(key, copy);
+        Ok(copy)
+    }
+
+    fn compute_monomorphize(
+        &mut self,
+        func: &CCIdx<DeclId<TyFunctionDeclaration>>,
+        type_arguments: &[TypeArgument],
+        requested_by: CollectionIndex,
+    ) -> Result<DeclId<TyFunctionDeclaration>, String> {
+        let signature = self.signature_of(&func.untyped())?;
+        if !signature.type_parameters.is_empty()
+            && !self.active.contains(&QueryKey::TypeOf(func.idx()))
+        {
+            self.type_of(&CCIdx::new(
+                TyDeclaration::Function(func.clone()),
+                func.idx(),
+            ))?;
+        }
+        let mut func_decl = (*de_get_function(self.session, *func.inner_ref())?).clone();
+        monomorphize(self.session, &mut func_decl, type_arguments)?;
+        de_add_monomorphized_function_copy(self.session, *func.inner_ref(), requested_by, func_decl)
+    }
+
+    fn find_impls(
+        &mut self,
+        type_id: TypeId,
+    ) -> Result<Vec<CCIdx<DeclId<TyFunctionDeclaration>>>, String> {
+        // collect the type that every trait impl implements for, before
+        // comparing them
+        for impl_id in self.trait_impls.clone().iter() {
+            self.impl_header_of(impl_id)?;
+        }
+        let mut methods = vec![];
+        for impl_id in de_impls_for_type(self.session, type_id)?.into_iter() {
+            methods.extend_from_slice(&de_get_trait_impl(self.session, impl_id)?.methods);
+        }
+        Ok(methods)
+    }
+
+    /// The type that the trait impl `impl_id` implements for, after doing type
+    /// collection on the trait impl.
+    fn impl_header_of(&mut self, impl_id: &CCIdx<DeclId<TyTraitImpl>>) -> Result<TypeId, String> {
+        if let Some(type_id) = self.cache.impl_headers.get(&impl_id.idx()) {
+            return Ok(*type_id);
+        }
+        self.enter(QueryKey::ImplHeaderOf(impl_id.idx()))?;
+        collect_types_trait_impl(self.session, self.cc, impl_id)?;
+        let type_id = de_get_trait_impl(self.session, *impl_id.inner_ref())
+            .map(|trait_impl| trait_impl.type_implementing_for);
+        self.exit();
+        let type_id = type_id?;
+        self.cache.impl_headers.insert(impl_id.idx(), type_id);
+        Ok(type_id)
+    }
+
+    fn type_check_node(&mut self, node: &CCIdx<TyNode>) -> Result<(), String> {
+        match node.inner_ref() {
+            TyNode::Declaration(decl) => self.type_of(decl).map(|_| ()),
+            TyNode::Expression(exp) | TyNode::ReturnStatement(exp) => {
+                self.type_of_expression(node.idx(), exp).map(|_| ())
+            }
+            TyNode::StarImport(_) => Ok(()),
+        }
+    }
+
+    fn type_of_expression(
+        &mut self,
+        index: CollectionIndex,
+        exp: &TyExpression,
+    ) -> Result<TypeId, String> {
+        self.memoized_type_of(index, |qe| {
+            analyze_expression(qe, index, &mut Namespace::default(), exp)?;
+            Ok(exp.type_id)
+        })
+    }
+
+    fn memoized_type_of<F>(&mut self, index: CollectionIndex, compute: F) -> Result<TypeId, String>
+    where
+        F: FnOnce(&mut Self) -> Result<TypeId, String>,
+    {
+        if let Some(type_id) = self.cache.types.get(&index) {
+            return Ok(*type_id);
+        }
+        self.enter(QueryKey::TypeOf(index))?;
+        let type_id = compute(self);
+        self.exit();
+        let type_id = type_id?;
+        self.cache.types.insert(index, type_id);
+        Ok(type_id)
+    }
+
+    fn enter(&mut self, key: QueryKey) -> Result<(), String> {
+        if let Some(position) = self.active.iter().position(|active| *active == key) {
+            let cycle = self.active[position..]
+                .iter()
+                .chain(std::iter::once(&key))
+                .map(|key| self.describe(key))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(format!(
+                "cycle detected when computing {}: {}",
+                self.describe(&key),
+                cycle
+            ));
+        }
+        self.active.push(key);
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.active.pop();
+    }
+
+    fn describe(&self, key: &QueryKey) -> String {
+        match key {
+            QueryKey::TypeOf(index) => format!("type_of({})", self.name_of(*index)),
+            QueryKey::SignatureOf(index) => format!("signature_of({})", self.name_of(*index)),
+            QueryKey::ImplHeaderOf(index) => {
+                format!("impl_header_of({})", self.name_of(*index))
+            }
+            QueryKey::ImplsFor(type_id) => format!("impls_for({})", type_id),
+            QueryKey::Monomorphize(index, _) => {
+                format!("monomorphize({})", self.name_of(*index))
+            }
+        }
+    }
+
+    fn name_of(&self, index: CollectionIndex) -> String {
+        match self.cc.get_node(index) {
+            CollectionNode::Variable(name, _)
+            | CollectionNode::Function(name, _)
+            | CollectionNode::Trait(name, _)
+            | CollectionNode::TraitFn(name, _)
+            | CollectionNode::TraitImpl(name, _)
+            | CollectionNode::Struct(name, _) => name.clone(),
+            node => node.to_string(),
+        }
+    }
+}
+
+fn find_trait_impls(
+    session: &Session,
+    nodes: &[CCIdx<TyNode>],
+    trait_impls: &mut Vec<CCIdx<DeclId<TyTraitImpl>>>,
+) {
+    for node in nodes.iter() {
+        if let TyNode::Declaration(decl) = node.inner_ref() {
+            match decl.inner_ref() {
+                TyDeclaration::Function(decl_id) => {
+                    let func_decl = de_get_function(session, *decl_id.inner_ref()).unwrap();
+                    find_trait_impls(session, &func_decl.body.inner_ref().contents, trait_impls);
+                }
+                TyDeclaration::TraitImpl(decl_id) => {
+                    trait_impls.push(decl_id.clone());
+                    for method_id in de_get_trait_impl(session, *decl_id.inner_ref())
+                        .unwrap()
+                        .methods
+                        .iter()
+                    {
+                        let method = de_get_function(session, *method_id.inner_ref()).unwrap();
+                        find_trait_impls(session, &method.body.inner_ref().contents, trait_impls);
+                    }
+                }
+                TyDeclaration::Variable(_) | TyDeclaration::Trait(_) | TyDeclaration::Struct(_) => {
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{collection_context::collection_index::CollectionIndex, type_system::type_id::TypeId};
+
+/// Identifies a single query made to the [QueryEngine](super::query_engine::QueryEngine).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum QueryKey {
+    /// The type of the declaration at the given index, which requires doing
+    /// type inference on it.
+    TypeOf(CollectionIndex),
+    /// The signature of the function at the given index, which requires doing
+    /// type collection on it.
+    SignatureOf(CollectionIndex),
+    /// The type that the trait impl at the given index implements for.
+    ImplHeaderOf(CollectionIndex),
+    /// The methods implemented for the given type, identified by its interned
+    /// id if it is concrete.
+    ImplsFor(TypeId),
+    /// The copy of the function at the given index for the given type
+    /// arguments, identified by their type variables.
+    Monomorphize(CollectionIndex, Vec<TypeId>),
+}
@@ -8,13 +8,13 @@ use crate::{
             de_insert_function, de_insert_struct, de_insert_trait, de_insert_trait_fn,
             de_insert_trait_impl,
         },
-        declaration_id::DeclarationId,
+        declaration_id::DeclId,
     },
     language::{
         parsed::{
             declaration::{
                 Declaration, FunctionDeclaration, FunctionParameter, StructDeclaration,
-                TraitDeclaration, TraitFn, TraitImpl, VariableDeclaration,
+                TraitDeclaration, TraitFn, TraitImpl, TypeParameter, VariableDeclaration,
             },
             Node,
         },
@@ -24,39 +24,49 @@ use crate::{
             TyVariableDeclaration,
         },
     },
-    type_system::type_engine::insert_type,
+    mangling::PathSegment,
+    session::Session,
+    type_system::{
+        type_engine::{insert_type, insert_type_in_scope},
+        type_info::TypeInfo,
+        type_parameter::TypeParameter as TyTypeParameter,
+    },
 };
 
 use super::{collect_graph_node, expression::collect_graph_exp};
 
 pub(super) fn collect_graph_decl(
+    session: &Session,
     cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
+    type_parameters: &[TyTypeParameter],
     decl: Declaration,
 ) -> CCIdx<TyDeclaration> {
     match decl {
         Declaration::Variable(var_decl) => {
-            let var_decl = collect_graph_var_decl(cc, var_decl);
+            let var_decl = collect_graph_var_decl(session, type_parameters, var_decl);
             let decl = TyDeclaration::Variable(var_decl.clone());
             let decl_idx = cc.add_node(CollectionNode::Variable(var_decl.name.clone(), var_decl));
             CCIdx::new(decl, decl_idx)
         }
         Declaration::Function(func_decl) => {
-            let func_decl_cc_idx = collect_graph_function(cc, func_decl);
+            let func_decl_cc_idx = collect_graph_function(session, cc, file, path, func_decl);
             let decl = TyDeclaration::Function(func_decl_cc_idx.clone());
             CCIdx::new(decl, func_decl_cc_idx.idx())
         }
         Declaration::Trait(trait_decl) => {
-            let trait_decl_cc_idx = collect_graph_trait(cc, trait_decl);
+            let trait_decl_cc_idx = collect_graph_trait(session, cc, trait_decl);
             let decl = TyDeclaration::Trait(trait_decl_cc_idx.clone());
             CCIdx::new(decl, trait_decl_cc_idx.idx())
         }
         Declaration::TraitImpl(trait_impl) => {
-            let trait_impl_cc_idx = collect_graph_trait_impl(cc, trait_impl);
+            let trait_impl_cc_idx = collect_graph_trait_impl(session, cc, file, path, trait_impl);
             let decl = TyDeclaration::TraitImpl(trait_impl_cc_idx.clone());
             CCIdx::new(decl, trait_impl_cc_idx.idx())
         }
         Declaration::Struct(struct_decl) => {
-            let struct_decl_cc_idx = collect_graph_struct(cc, struct_decl);
+            let struct_decl_cc_idx = collect_graph_struct(session, cc, file, path, struct_decl);
             let decl = TyDeclaration::Struct(struct_decl_cc_idx.clone());
             CCIdx::new(decl, struct_decl_cc_idx.idx())
         }
@@ -64,40 +74,79 @@ pub(super) fn collect_graph_decl(
 }
 
 fn collect_graph_var_decl(
-    cc: &mut CollectionContext,
+    session: &Session,
+    type_parameters: &[TyTypeParameter],
     var_decl: VariableDeclaration,
 ) -> TyVariableDeclaration {
     TyVariableDeclaration {
         name: var_decl.name,
-        body: collect_graph_exp(cc, var_decl.body),
-        type_ascription: insert_type(var_decl.type_ascription),
+        body: collect_graph_exp(session, type_parameters, var_decl.body),
+        type_ascription: insert_type_in_scope(session, var_decl.type_ascription, type_parameters),
     }
 }
 
 fn collect_graph_function(
+    session: &Session,
     cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
     func_decl: FunctionDeclaration,
-) -> CCIdx<DeclarationId> {
-    // collect the parameters
+) -> CCIdx<DeclId<TyFunctionDeclaration>> {
+    // collect the type parameters, which the types in the rest of the function
+    // refer to by name
+    let type_parameters = func_decl
+        .type_parameters
+        .into_iter()
+        .map(|type_param| collect_graph_type_parameter(session, type_param))
+        .collect::<Vec<_>>();
+
+    // collect the parameters, adding them to the graph as variables
     let parameters = func_decl
         .parameters
         .into_iter()
-        .map(collect_graph_function_parameter)
+        .map(|param| {
+            let param = collect_graph_function_parameter(session, &type_parameters, param);
+            let param_idx = cc.add_node(CollectionNode::Variable(
+                param.name.clone(),
+                (&param).into(),
+            ));
+            CCIdx::new(param, param_idx)
+        })
         .collect::<Vec<_>>();
 
-    // collect the body
-    let body = collect_graph_code_block(cc, func_decl.body);
+    // connect every parameter to the parameter before it
+    CCIdx::add_edges_sequential(&parameters, CollectionEdge::SequentialScope, cc);
+
+    // collect the body, whose declarations are declared in the function
+    let mut body_path = path.to_vec();
+    body_path.push(PathSegment::named(&func_decl.name));
+    let body = collect_graph_code_block(
+        session,
+        cc,
+        file,
+        &body_path,
+        &type_parameters,
+        func_decl.body,
+    );
+
+    // connect the start of the body to the last parameter
+    if let (Some(first), Some(last)) = (body.inner_ref().contents.first(), parameters.last()) {
+        CCIdx::add_edge(first, last, CollectionEdge::SequentialScope, cc);
+    }
 
     let func_decl = TyFunctionDeclaration {
         name: func_decl.name,
-        type_parameters: func_decl.type_parameters,
-        parameters,
+        parameters: parameters
+            .iter()
+            .map(|param| param.inner_ref().clone())
+            .collect(),
         body,
-        return_type: insert_type(func_decl.return_type),
+        return_type: insert_type_in_scope(session, func_decl.return_type, &type_parameters),
+        type_parameters,
     };
 
     // insert the function into the declaration engine
-    let func_decl_id = de_insert_function(func_decl.clone());
+    let func_decl_id = de_insert_function(session, func_decl.clone());
 
     // add the function id to the graph
     let func_decl_idx = cc.add_node(CollectionNode::Function(func_decl.name, func_decl_id));
@@ -113,18 +162,33 @@ fn collect_graph_function(
         cc,
     );
 
+    // add an edge from every parameter to the function declaration
+    CCIdx::add_edges_many_to_one(
+        &parameters,
+        &func_decl_cc_idx,
+        CollectionEdge::ScopedChild,
+        cc,
+    );
+
     func_decl_cc_idx
 }
 
-fn collect_graph_code_block(cc: &mut CollectionContext, nodes: Vec<Node>) -> CCIdx<TyCodeBlock> {
+fn collect_graph_code_block(
+    session: &Session,
+    cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
+    type_parameters: &[TyTypeParameter],
+    nodes: Vec<Node>,
+) -> CCIdx<TyCodeBlock> {
     // collect the nodes
     let nodes = nodes
         .into_iter()
-        .map(|node| collect_graph_node(cc, node))
+        .map(|node| collect_graph_node(session, cc, file, path, type_parameters, node))
         .collect::<Vec<_>>();
 
-    // for every node in this scope, connect them under the same shared scope
-    CCIdx::add_edges_many(&nodes, CollectionEdge::SharedScope, cc);
+    // connect every node to the node before it, for order-sensitive scoping
+    CCIdx::add_edges_sequential(&nodes, CollectionEdge::SequentialScope, cc);
 
     let code_block = TyCodeBlock { contents: nodes };
 
@@ -145,22 +209,40 @@ fn collect_graph_code_block(cc: &mut CollectionContext, nodes: Vec<Node>) -> CCI
     code_block_cc_idx
 }
 
-fn collect_graph_function_parameter(function_parameter: FunctionParameter) -> TyFunctionParameter {
+fn collect_graph_function_parameter(
+    session: &Session,
+    type_parameters: &[TyTypeParameter],
+    function_parameter: FunctionParameter,
+) -> TyFunctionParameter {
     TyFunctionParameter {
         name: function_parameter.name,
-        type_id: insert_type(function_parameter.type_info),
+        type_id: insert_type_in_scope(session, function_parameter.type_info, type_parameters),
+    }
+}
+
+fn collect_graph_type_parameter(session: &Session, type_param: TypeParameter) -> TyTypeParameter {
+    TyTypeParameter {
+        type_id: insert_type(
+            session,
+            TypeInfo::UnknownGeneric {
+                name: type_param.name.clone(),
+            },
+        ),
+        name: type_param.name,
+        trait_constraint: type_param.trait_constraint,
     }
 }
 
 fn collect_graph_trait(
+    session: &Session,
     cc: &mut CollectionContext,
     trait_decl: TraitDeclaration,
-) -> CCIdx<DeclarationId> {
+) -> CCIdx<DeclId<TyTraitDeclaration>> {
     // connect the interface surface
     let interface_surface = trait_decl
         .interface_surface
         .into_iter()
-        .map(|trait_fn| collect_graph_trait_fn(cc, trait_fn))
+        .map(|trait_fn| collect_graph_trait_fn(session, cc, trait_fn))
         .collect::<Vec<_>>();
 
     let trait_decl = TyTraitDeclaration {
@@ -169,7 +251,7 @@ fn collect_graph_trait(
     };
 
     // insert the trait into the declaration engine
-    let trait_decl_id = de_insert_trait(trait_decl.clone());
+    let trait_decl_id = de_insert_trait(session, trait_decl.clone());
 
     // add the trait to the graph
     let trait_decl_idx = cc.add_node(CollectionNode::Trait(trait_decl.name, trait_decl_id));
@@ -177,13 +259,6 @@ fn collect_graph_trait(
     // create an Idx for the trait
     let trait_decl_cc_idx = CCIdx::new(trait_decl_id, trait_decl_idx);
 
-    // connect every trait fn in the interface surface
-    CCIdx::add_edges_many(
-        &trait_decl.interface_surface,
-        CollectionEdge::SharedScope,
-        cc,
-    );
-
     // connect every trait fn to the trait decl
     CCIdx::add_edges_many_to_one(
         &trait_decl.interface_surface,
@@ -195,22 +270,26 @@ fn collect_graph_trait(
     trait_decl_cc_idx
 }
 
-fn collect_graph_trait_fn(cc: &mut CollectionContext, trait_fn: TraitFn) -> CCIdx<DeclarationId> {
+fn collect_graph_trait_fn(
+    session: &Session,
+    cc: &mut CollectionContext,
+    trait_fn: TraitFn,
+) -> CCIdx<DeclId<TyTraitFn>> {
     // collect the parameters
     let parameters = trait_fn
         .parameters
         .into_iter()
-        .map(collect_graph_function_parameter)
+        .map(|param| collect_graph_function_parameter(session, &[], param))
         .collect::<Vec<_>>();
 
     let trait_fn = TyTraitFn {
         name: trait_fn.name,
         parameters,
-        return_type: insert_type(trait_fn.return_type),
+        return_type: insert_type(session, trait_fn.return_type),
     };
 
     // insert the trait fn into the declaration engine
-    let trait_fn_id = de_insert_trait_fn(trait_fn.clone());
+    let trait_fn_id = de_insert_trait_fn(session, trait_fn.clone());
 
     // add the trait fn to the graph
     let trait_fn_idx = cc.add_node(CollectionNode::TraitFn(trait_fn.name, trait_fn_id));
@@ -220,29 +299,39 @@ fn collect_graph_trait_fn(cc: &mut CollectionContext, trait_fn: TraitFn) -> CCId
 }
 
 fn collect_graph_trait_impl(
+    session: &Session,
     cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
     trait_impl: TraitImpl,
-) -> CCIdx<DeclarationId> {
+) -> CCIdx<DeclId<TyTraitImpl>> {
     if !trait_impl.type_parameters.is_empty() {
         panic!()
     }
 
-    // collect the methods
+    let type_implementing_for = insert_type(session, trait_impl.type_implementing_for);
+
+    // collect the methods, which are declared in the trait impl
+    let mut methods_path = path.to_vec();
+    methods_path.push(PathSegment::Impl {
+        trait_name: trait_impl.trait_name.clone(),
+        type_implementing_for,
+    });
     let methods = trait_impl
         .methods
         .into_iter()
-        .map(|method| collect_graph_function(cc, method))
+        .map(|method| collect_graph_function(session, cc, file, &methods_path, method))
         .collect::<Vec<_>>();
 
     let trait_impl = TyTraitImpl {
         trait_name: trait_impl.trait_name,
-        type_implementing_for: insert_type(trait_impl.type_implementing_for),
+        type_implementing_for,
         type_parameters: vec![],
         methods,
     };
 
     // insert the trait into the declaration engine
-    let trait_impl_id = de_insert_trait_impl(trait_impl.clone());
+    let trait_impl_id = de_insert_trait_impl(session, trait_impl.clone());
 
     // add the trait to the graph
     let trait_impl_idx = cc.add_node(CollectionNode::TraitImpl(
@@ -253,9 +342,6 @@ fn collect_graph_trait_impl(
     // create an Idx for the trait
     let trait_impl_cc_idx = CCIdx::new(trait_impl_id, trait_impl_idx);
 
-    // connect every method
-    CCIdx::add_edges_many(&trait_impl.methods, CollectionEdge::SharedScope, cc);
-
     // connect every method to the trait impl
     CCIdx::add_edges_many_to_one(
         &trait_impl.methods,
@@ -268,27 +354,39 @@ fn collect_graph_trait_impl(
 }
 
 fn collect_graph_struct(
+    session: &Session,
     cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
     struct_decl: StructDeclaration,
-) -> CCIdx<DeclarationId> {
+) -> CCIdx<DeclId<TyStructDeclaration>> {
+    // collect the type parameters, which the fields refer to by name
+    let type_parameters = struct_decl
+        .type_parameters
+        .into_iter()
+        .map(|type_param| collect_graph_type_parameter(session, type_param))
+        .collect::<Vec<_>>();
+
     // collect the fields
     let fields = struct_decl
         .fields
         .into_iter()
         .map(|field| TyStructField {
             name: field.name,
-            type_id: insert_type(field.type_info),
+            type_id: insert_type_in_scope(session, field.type_info, &type_parameters),
         })
         .collect::<Vec<_>>();
 
     let struct_decl = TyStructDeclaration {
         name: struct_decl.name,
-        type_parameters: struct_decl.type_parameters,
+        file: file.to_string(),
+        path: path.to_vec(),
+        type_parameters,
         fields,
     };
 
     // insert the struct into the declaration engine
-    let struct_decl_id = de_insert_struct(struct_decl.clone());
+    let struct_decl_id = de_insert_struct(session, struct_decl.clone());
 
     // add the trait to the graph
     let struct_decl_idx = cc.add_node(CollectionNode::Struct(struct_decl.name, struct_decl_id));
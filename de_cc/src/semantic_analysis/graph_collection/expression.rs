@@ -1,21 +1,30 @@
 use crate::{
-    collection_context::collection_context::CollectionContext,
     language::{
         parsed::expression::Expression,
         ty::typed_expression::{TyExpression, TyExpressionVariant, TyStructExpressionField},
     },
-    type_system::{type_engine::insert_type, type_info::TypeInfo},
+    session::Session,
+    type_system::{
+        type_argument::TypeArgument,
+        type_engine::{insert_type, insert_type_in_scope},
+        type_info::TypeInfo,
+        type_parameter::TypeParameter,
+    },
 };
 
-pub(super) fn collect_graph_exp(cc: &mut CollectionContext, exp: Expression) -> TyExpression {
+pub(super) fn collect_graph_exp(
+    session: &Session,
+    type_parameters: &[TypeParameter],
+    exp: Expression,
+) -> TyExpression {
     match exp {
         Expression::Literal { value } => {
-            let type_id = insert_type(value.to_type());
+            let type_id = insert_type(session, value.to_type());
             let variant = TyExpressionVariant::Literal { value };
             TyExpression { variant, type_id }
         }
         Expression::Variable { name } => {
-            let type_id = insert_type(TypeInfo::Unknown);
+            let type_id = insert_type(session, TypeInfo::Unknown);
             let variant = TyExpressionVariant::Variable { name };
             TyExpression { variant, type_id }
         }
@@ -32,18 +41,22 @@ pub(super) fn collect_graph_exp(cc: &mut CollectionContext, exp: Expression) ->
             // transform the arguments into Ty AST nodes
             let new_arguments = arguments
                 .into_iter()
-                .map(|argument| collect_graph_exp(cc, argument))
+                .map(|exp| collect_graph_exp(session, type_parameters, exp))
                 .collect::<Vec<_>>();
 
             // return!
             let variant = TyExpressionVariant::FunctionApplication {
                 name,
-                type_arguments,
+                type_arguments: collect_graph_type_arguments(
+                    session,
+                    type_parameters,
+                    type_arguments,
+                ),
                 arguments: new_arguments,
             };
             TyExpression {
                 variant,
-                type_id: insert_type(TypeInfo::Unknown),
+                type_id: insert_type(session, TypeInfo::Unknown),
             }
         }
         Expression::MethodCall {
@@ -60,19 +73,23 @@ pub(super) fn collect_graph_exp(cc: &mut CollectionContext, exp: Expression) ->
             // transform the arguments into Ty AST nodes
             let new_arguments = arguments
                 .into_iter()
-                .map(|argument| collect_graph_exp(cc, argument))
+                .map(|exp| collect_graph_exp(session, type_parameters, exp))
                 .collect::<Vec<_>>();
 
             // return!
             let variant = TyExpressionVariant::MethodCall {
                 parent_name,
                 func_name,
-                type_arguments,
+                type_arguments: collect_graph_type_arguments(
+                    session,
+                    type_parameters,
+                    type_arguments,
+                ),
                 arguments: new_arguments,
             };
             TyExpression {
                 variant,
-                type_id: insert_type(TypeInfo::Unknown),
+                type_id: insert_type(session, TypeInfo::Unknown),
             }
         }
         Expression::Struct {
@@ -90,20 +107,37 @@ pub(super) fn collect_graph_exp(cc: &mut CollectionContext, exp: Expression) ->
                 .into_iter()
                 .map(|field| TyStructExpressionField {
                     name: field.name,
-                    value: collect_graph_exp(cc, field.value),
+                    value: collect_graph_exp(session, type_parameters, field.value),
                 })
                 .collect::<Vec<_>>();
 
             // return!
             let variant = TyExpressionVariant::Struct {
                 struct_name,
-                type_arguments,
+                type_arguments: collect_graph_type_arguments(
+                    session,
+                    type_parameters,
+                    type_arguments,
+                ),
                 fields: typed_fields,
             };
             TyExpression {
                 variant,
-                type_id: insert_type(TypeInfo::Unknown),
+                type_id: insert_type(session, TypeInfo::Unknown),
             }
         }
     }
 }
+
+fn collect_graph_type_arguments(
+    session: &Session,
+    type_parameters: &[TypeParameter],
+    type_arguments: Vec<TypeInfo>,
+) -> Vec<TypeArgument> {
+    type_arguments
+        .into_iter()
+        .map(|type_info| TypeArgument {
+            type_id: insert_type_in_scope(session, type_info, type_parameters),
+        })
+        .collect()
+}
@@ -3,8 +3,9 @@
 //!
 //! In transforming the untyped AST into a typeable AST, this module:
 //! 1. inserts instances of [TypeInfo](crate::type_system::type_info::TypeInfo)
-//!     into the [TypeEngine](crate::type_system::type_engine::TypeEngine)
+//!    into the [TypeEngine](crate::type_system::type_engine::TypeEngine)
 //! 2. inserts declarations into the [DeclarationEngine](crate::declaration_engine::declaration_engine::DeclarationEngine)
+//! 3. refers the names of type parameters to the type parameters that declare them
 //!
 //! This module does not *and should not*:
 //! - evaluate types in any way
@@ -26,18 +27,35 @@ use crate::{
         parsed::{Application, File, Node},
         ty::{TyApplication, TyFile, TyNode},
     },
+    mangling::PathSegment,
+    session::Session,
+    type_system::type_parameter::TypeParameter,
 };
 
 /// Takes an untyped [Application] struct and transforms it into a typeable [TyApplication],
 /// wrapped in a [CCIdx] representing its index in the [CollectionContext]
-pub(crate) fn collect_graph(cc: &mut CollectionContext, app: Application) -> CCIdx<TyApplication> {
+pub(crate) fn collect_graph(
+    session: &Session,
+    cc: &mut CollectionContext,
+    app: Application,
+) -> Result<CCIdx<TyApplication>, String> {
     // create graph nodes for each of the files
     let file_idxs = app
         .files
         .into_iter()
-        .map(|file| collect_graph_file(cc, file))
+        .map(|file| collect_graph_file(session, cc, file))
         .collect::<Vec<_>>();
 
+    add_graph_application(session, cc, file_idxs)
+}
+
+/// Creates a graph node for an application made up of the already collected
+/// `files`, and builds the symbol tables for the now complete graph.
+pub(crate) fn add_graph_application(
+    session: &Session,
+    cc: &mut CollectionContext,
+    file_idxs: Vec<CCIdx<TyFile>>,
+) -> Result<CCIdx<TyApplication>, String> {
     // create a graph node for this application
     let app = TyApplication {
         files: file_idxs.clone(),
@@ -48,23 +66,36 @@ pub(crate) fn collect_graph(cc: &mut CollectionContext, app: Application) -> CCI
     // add a graph edge from every file to the application
     CCIdx::add_edges_many_to_one(&file_idxs, &cc_idx, CollectionEdge::ApplicationContents, cc);
 
-    cc_idx
+    // now that the graph is complete, build the symbol tables for every scope
+    cc.build_symbol_tables(session)?;
+
+    Ok(cc_idx)
 }
 
-fn collect_graph_file(cc: &mut CollectionContext, file: File) -> CCIdx<TyFile> {
+fn collect_graph_file(session: &Session, cc: &mut CollectionContext, file: File) -> CCIdx<TyFile> {
     // create graph nodes for the ast nodes
     let nodes = file
         .nodes
         .into_iter()
-        .map(|node| collect_graph_node(cc, node))
+        .map(|node| collect_graph_node(session, cc, &file.name, &[], &[], node))
         .collect::<Vec<_>>();
 
-    // for every ast node in this scope, connect them under the same shared scope with graph edges
-    CCIdx::add_edges_many(&nodes, CollectionEdge::SharedScope, cc);
+    add_graph_file(cc, file.name, nodes)
+}
+
+/// Creates a graph node for the file `name` made up of the already collected
+/// ast `nodes`.
+pub(crate) fn add_graph_file(
+    cc: &mut CollectionContext,
+    name: String,
+    nodes: Vec<CCIdx<TyNode>>,
+) -> CCIdx<TyFile> {
+    // connect every ast node to the ast node before it, for order-sensitive scoping
+    CCIdx::add_edges_sequential(&nodes, CollectionEdge::SequentialScope, cc);
 
     // create a graph node for this file
     let file = TyFile {
-        name: file.name,
+        name,
         nodes: nodes.clone(),
     };
     let file_idx = cc.add_node(file.clone().into());
@@ -80,7 +111,17 @@ fn collect_graph_file(cc: &mut CollectionContext, file: File) -> CCIdx<TyFile> {
     cc_idx
 }
 
-fn collect_graph_node(cc: &mut CollectionContext, node: Node) -> CCIdx<TyNode> {
+/// Collects `node`, which is in the body of the item at `path` within `file`,
+/// or at the top level of `file` if `path` is empty. The surrounding function
+/// has the type parameters `type_parameters`, if any.
+pub(crate) fn collect_graph_node(
+    session: &Session,
+    cc: &mut CollectionContext,
+    file: &str,
+    path: &[PathSegment],
+    type_parameters: &[TypeParameter],
+    node: Node,
+) -> CCIdx<TyNode> {
     match node {
         Node::StarImport(filename) => {
             let node = TyNode::StarImport(filename.clone());
@@ -88,18 +129,18 @@ fn collect_graph_node(cc: &mut CollectionContext, node: Node) -> CCIdx<TyNode> {
             CCIdx::new(node, node_idx)
         }
         Node::Declaration(decl) => {
-            let decl_cc_idx = collect_graph_decl(cc, decl);
+            let decl_cc_idx = collect_graph_decl(session, cc, file, path, type_parameters, decl);
             let node = TyNode::Declaration(decl_cc_idx.clone());
             CCIdx::new(node, decl_cc_idx.idx())
         }
         Node::Expression(expression) => {
-            let exp = collect_graph_exp(cc, expression);
+            let exp = collect_graph_exp(session, type_parameters, expression);
             let node = TyNode::Expression(exp.clone());
             let node_idx = cc.add_node(CollectionNode::Expression(exp));
             CCIdx::new(node, node_idx)
         }
         Node::ReturnStatement(expression) => {
-            let exp = collect_graph_exp(cc, expression);
+            let exp = collect_graph_exp(session, type_parameters, expression);
             let node = TyNode::ReturnStatement(exp.clone());
             let node_idx = cc.add_node(CollectionNode::Return(exp));
             CCIdx::new(node, node_idx)
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use crate::{
+    collection_context::collection_index::{CCIdx, CollectionIndex},
+    declaration_engine::{declaration_engine::*, declaration_id::DeclId},
+    language::ty::{
+        typed_declaration::{TyDeclaration, TyFunctionDeclaration},
+        TyNode,
+    },
+    query_engine::query_engine::QueryEngine,
+    session::Session,
+    type_system::{
+        type_argument::TypeArgument,
+        type_engine::{
+            are_equivalent_types, canonical_type_id, concrete_type_arguments, insert_type,
+            look_up_type_id, monomorphize, unify_types,
+        },
+        type_id::TypeId,
+        type_info::TypeInfo,
+        type_mapping::TypeMapping,
+    },
+    types::copy_types::CopyTypes,
+};
+
+use super::{expression::collect_expression, Instances};
+
+/// Collects the instances needed by a use of the function `decl_id` by the node
+/// `requested_by`, that is given `arguments`, and whose value has the type
+/// `return_type`.
+///
+/// A generic function is instantiated with the type arguments inferred from the
+/// use. It is an error if they can not be inferred, or if more instances of the
+/// same function are nested within each other than the instantiation depth
+/// limit of the [Session](crate::session::Session).
+pub(super) fn collect_function_use(
+    qe: &mut QueryEngine,
+    instances: &mut Instances,
+    decl_id: &CCIdx<DeclId<TyFunctionDeclaration>>,
+    requested_by: CollectionIndex,
+    arguments: &[TypeId],
+    return_type: Option<TypeId>,
+) -> Result<(), String> {
+    let session = qe.session();
+    let original_id = *decl_id.inner_ref();
+    let func_decl = de_get_function(session, original_id)?;
+    if func_decl.type_parameters.is_empty() {
+        if !instances.walked.insert(original_id.untyped()) {
+            return Ok(());
+        }
+        qe.type_of_decl_id(&decl_id.untyped())?;
+        let name = function_name(session, original_id, &func_decl)?;
+        instances.stack.push((original_id.untyped(), name));
+        collect_function(qe, instances, decl_id.idx(), &func_decl)?;
+        instances.stack.pop();
+        return Ok(());
+    }
+
+    // instantiate every type parameter of the signature with a new type
+    // variable, and infer them from the use, without copying the function
+    let signature = qe.signature_of(&decl_id.untyped())?;
+    let type_mapping = signature
+        .type_parameters
+        .iter()
+        .map(|type_parameter| {
+            (
+                type_parameter.type_id,
+                insert_type(session, TypeInfo::Unknown),
+            )
+        })
+        .collect::<TypeMapping>();
+    for (argument, parameter) in arguments.iter().zip(signature.parameters.iter()) {
+        let mut parameter_type = parameter.type_id;
+        parameter_type.copy_types(session, &type_mapping);
+        unify_types(session, *argument, parameter_type)?;
+    }
+    if let Some(return_type) = return_type {
+        let mut signature_return_type = signature.return_type;
+        signature_return_type.copy_types(session, &type_mapping);
+        unify_types(session, signature_return_type, return_type)?;
+    }
+
+    let type_arguments = match signature
+        .type_parameters
+        .iter()
+        .map(|type_parameter| canonical_type_id(session, type_mapping[&type_parameter.type_id]))
+        .collect::<Result<Option<Vec<_>>, _>>()?
+    {
+        Some(type_arguments) => type_arguments,
+        None => {
+            let used_in = instances
+                .stack
+                .last()
+                .map(|(_, description)| description.as_str())
+                .unwrap_or("the application");
+            return Err(format!(
+                "could not infer the type arguments of {} used in {}",
+                function_name(session, original_id, &func_decl)?,
+                used_in
+            ));
+        }
+    };
+    let description = format!(
+        "{}<{}>",
+        function_name(session, original_id, &func_decl)?,
+        type_arguments
+            .iter()
+            .map(|type_argument| type_argument.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if !instances
+        .found
+        .insert((original_id.untyped(), type_arguments.clone()))
+    {
+        de_find_monomorphized_function_copy(session, original_id, &type_arguments, requested_by);
+        return Ok(());
+    }
+    check_trait_constraints(qe, decl_id, &func_decl, &type_arguments, &description)?;
+
+    // a function that calls itself with a growing type argument would be
+    // instantiated forever
+    let limit = session.instantiation_depth_limit;
+    let depth = instances
+        .stack
+        .iter()
+        .filter(|(id, _)| *id == original_id.untyped())
+        .count();
+    if depth >= limit {
+        let chain = instances
+            .stack
+            .iter()
+            .map(|(_, description)| description.as_str())
+            .chain(std::iter::once(description.as_str()))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(format!(
+            "reached the instantiation depth limit of {} when instantiating {}: {}",
+            limit, description, chain
+        ));
+    }
+
+    // reuse the copy for the same type arguments, and only make a new copy for
+    // the first use of them
+    let copy_id = match de_find_monomorphized_function_copy(
+        session,
+        original_id,
+        &type_arguments,
+        requested_by,
+    ) {
+        Some(copy_id) => copy_id,
+        None => {
+            let type_arguments = type_arguments
+                .into_iter()
+                .map(|type_id| TypeArgument { type_id })
+                .collect::<Vec<_>>();
+            qe.monomorphize(decl_id, &type_arguments, requested_by)?
+        }
+    };
+    let instance = de_get_function(session, copy_id)?;
+
+    instances.copies.insert(copy_id.untyped());
+    instances.stack.push((original_id.untyped(), description));
+    collect_function(qe, instances, decl_id.idx(), &instance)?;
+    instances.stack.pop();
+    Ok(())
+}
+
+/// The name of the function `decl_id` for use in messages, which is qualified by
+/// the trait impl that declares it for a method.
+fn function_name(
+    session: &Session,
+    decl_id: DeclId<TyFunctionDeclaration>,
+    func_decl: &TyFunctionDeclaration,
+) -> Result<String, String> {
+    match de_parent_of(session, decl_id.untyped()) {
+        Some(parent) => {
+            let trait_impl = de_get_trait_impl(session, de_expect_decl_id(session, parent)?)?;
+            Ok(format!(
+                "<{} as {}>::{}",
+                trait_impl.type_implementing_for, trait_impl.trait_name, func_decl.name
+            ))
+        }
+        None => Ok(func_decl.name.clone()),
+    }
+}
+
+/// Checks that the type arguments of the instance `description` of the generic
+/// function `decl_id` implement the traits that their type parameters are
+/// constrained by.
+fn check_trait_constraints(
+    qe: &mut QueryEngine,
+    decl_id: &CCIdx<DeclId<TyFunctionDeclaration>>,
+    func_decl: &TyFunctionDeclaration,
+    type_arguments: &[TypeId],
+    description: &str,
+) -> Result<(), String> {
+    let session = qe.session();
+    for (type_parameter, type_argument) in
+        func_decl.type_parameters.iter().zip(type_arguments.iter())
+    {
+        let constraint = match &type_parameter.trait_constraint {
+            Some(constraint) => constraint,
+            None => continue,
+        };
+        let trait_id = qe
+            .cc()
+            .get_symbol(decl_id.idx(), &constraint.trait_name)?
+            .expect(session)?
+            .inner();
+        let mut implemented = false;
+        for impl_id in de_impls_of_trait(session, trait_id).into_iter() {
+            let trait_impl = de_get_trait_impl(session, impl_id)?;
+            implemented |=
+                are_equivalent_types(session, trait_impl.type_implementing_for, *type_argument)?;
+        }
+        if !implemented {
+            return Err(format!(
+                "the trait {} is not implemented for {}, which instantiating {} requires",
+                constraint.trait_name, type_argument, description
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Collects the instances used by the signature and the body of `func_decl`,
+/// which is declared by the node `requested_by`.
+fn collect_function(
+    qe: &mut QueryEngine,
+    instances: &mut Instances,
+    requested_by: CollectionIndex,
+    func_decl: &TyFunctionDeclaration,
+) -> Result<(), String> {
+    // the types of the variables in scope, for finding the methods called on them
+    let mut variables = HashMap::new();
+    for parameter in func_decl.parameters.iter() {
+        collect_type(qe, instances, requested_by, parameter.type_id)?;
+        variables.insert(parameter.name.clone(), parameter.type_id);
+    }
+    collect_type(qe, instances, requested_by, func_decl.return_type)?;
+    for node in func_decl.body.inner_ref().contents.iter() {
+        match node.inner_ref() {
+            TyNode::Declaration(decl) => match decl.inner_ref() {
+                TyDeclaration::Variable(var_decl) => {
+                    collect_expression(qe, instances, &variables, decl.idx(), &var_decl.body)?;
+                    collect_type(qe, instances, decl.idx(), var_decl.type_ascription)?;
+                    variables.insert(var_decl.name.clone(), var_decl.type_ascription);
+                }
+                // nested declarations are only collected if they are used
+                TyDeclaration::Function(_)
+                | TyDeclaration::Trait(_)
+                | TyDeclaration::TraitImpl(_)
+                | TyDeclaration::Struct(_) => {}
+            },
+            TyNode::Expression(exp) | TyNode::ReturnStatement(exp) => {
+                collect_expression(qe, instances, &variables, node.idx(), exp)?;
+            }
+            TyNode::StarImport(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Collects the instances of the generic structs that `type_id` is made of,
+/// which is used by the node `requested_by`.
+pub(super) fn collect_type(
+    qe: &mut QueryEngine,
+    instances: &mut Instances,
+    requested_by: CollectionIndex,
+    type_id: TypeId,
+) -> Result<(), String> {
+    let session = qe.session();
+    let (decl_id, type_parameters, fields) = match &*look_up_type_id(session, type_id)? {
+        TypeInfo::Struct {
+            decl_id,
+            type_parameters,
+            fields,
+            ..
+        } => (*decl_id, type_parameters.clone(), fields.clone()),
+        _ => return Ok(()),
+    };
+    let type_arguments = match concrete_type_arguments(session, &type_parameters)? {
+        Some(type_arguments) => type_arguments,
+        None => return Ok(()),
+    };
+    // the types inside of a struct that has been found have been collected too
+    if !instances.found.insert((decl_id.untyped(), type_arguments)) {
+        return Ok(());
+    }
+    for type_id in type_parameters
+        .iter()
+        .map(|type_parameter| type_parameter.type_id)
+        .chain(fields.iter().map(|field| field.type_id))
+    {
+        collect_type(qe, instances, requested_by, type_id)?;
+    }
+    if type_parameters.is_empty() {
+        return Ok(());
+    }
+
+    let mut instance = (*de_get_struct(session, decl_id)?).clone();
+    let type_arguments = type_parameters
+        .iter()
+        .map(|type_parameter| TypeArgument {
+            type_id: type_parameter.type_id,
+        })
+        .collect::<Vec<_>>();
+    monomorphize(session, &mut instance, &type_arguments)?;
+    let copy_id = de_add_monomorphized_struct_copy(session, decl_id, requested_by, instance)?;
+    instances.copies.insert(copy_id.untyped());
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::{
+    collection_context::collection_index::CollectionIndex,
+    language::ty::typed_expression::{TyExpression, TyExpressionVariant},
+    query_engine::query_engine::QueryEngine,
+    type_system::type_id::TypeId,
+};
+
+use super::{
+    declaration::{collect_function_use, collect_type},
+    Instances,
+};
+
+/// Collects the instances used by `exp`, where `variables` are the types of
+/// the variables in scope.
+pub(super) fn collect_expression(
+    qe: &mut QueryEngine,
+    instances: &mut Instances,
+    variables: &HashMap<String, TypeId>,
+    current_index: CollectionIndex,
+    exp: &TyExpression,
+) -> Result<(), String> {
+    collect_type(qe, instances, current_index, exp.type_id)?;
+    match &exp.variant {
+        TyExpressionVariant::Literal { .. }
+        | TyExpressionVariant::Variable { .. }
+        | TyExpressionVariant::FunctionParameter => Ok(()),
+        TyExpressionVariant::FunctionApplication {
+            name, arguments, ..
+        } => {
+            for argument in arguments.iter() {
+                collect_expression(qe, instances, variables, current_index, argument)?;
+            }
+            let decl_id = qe
+                .cc()
+                .get_symbol(current_index, name)?
+                .expect(qe.session())?;
+            let arguments = arguments
+                .iter()
+                .map(|argument| argument.type_id)
+                .collect::<Vec<_>>();
+            collect_function_use(
+                qe,
+                instances,
+                &decl_id,
+                current_index,
+                &arguments,
+                Some(exp.type_id),
+            )
+        }
+        TyExpressionVariant::Struct { fields, .. } => {
+            for field in fields.iter() {
+                collect_expression(qe, instances, variables, current_index, &field.value)?;
+            }
+            Ok(())
+        }
+        TyExpressionVariant::MethodCall {
+            parent_name,
+            func_name,
+            arguments,
+            ..
+        } => {
+            for argument in arguments.iter() {
+                collect_expression(qe, instances, variables, current_index, argument)?;
+            }
+            let parent_type = match variables.get(parent_name) {
+                Some(type_id) => *type_id,
+                None => {
+                    qe.cc()
+                        .get_variable(current_index, parent_name)?
+                        .type_ascription
+                }
+            };
+            let method_id = qe.method_id_of(parent_type, func_name)?;
+            let arguments = arguments
+                .iter()
+                .map(|argument| argument.type_id)
+                .collect::<Vec<_>>();
+            collect_function_use(
+                qe,
+                instances,
+                &method_id,
+                current_index,
+                &arguments,
+                Some(exp.type_id),
+            )
+        }
+    }
+}
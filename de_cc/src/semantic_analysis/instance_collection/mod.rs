@@ -0,0 +1,273 @@
+//! This module collects the instances of generic declarations that the
+//! application needs.
+//!
+//! Starting from the `main` function of every file, the bodies of the
+//! functions that are reachable are walked, and every generic function, struct,
+//! and method that they use is instantiated with the type arguments of that
+//! use. The bodies of these instances are walked in turn, so generic code that
+//! is only used by other generic code is instantiated too, and generic code
+//! that is never reached is not instantiated at all.
+//!
+//! Instance collection is done after type inference, on the inferred bodies.
+
+mod declaration;
+mod expression;
+
+use std::{collections::HashSet, sync::Arc};
+
+use declaration::*;
+
+use crate::{
+    declaration_engine::{
+        declaration_engine::*,
+        declaration_id::{DeclId, DeclarationId},
+        declaration_wrapper::Declaration,
+        instantiation::Instantiation,
+    },
+    language::ty::{
+        typed_declaration::{TyDeclaration, TyFunctionDeclaration, TyStructDeclaration},
+        TyNode,
+    },
+    query_engine::query_engine::QueryEngine,
+    session::Session,
+    type_system::type_id::TypeId,
+};
+
+/// The instances of the generic declarations of an application, as found by
+/// [collect_instances].
+#[derive(Default)]
+pub(crate) struct Instances {
+    // declaration_id of every monomorphized copy that is an instance
+    copies: HashSet<DeclarationId>,
+    // (declaration_id, type arguments) of every instance found so far
+    found: HashSet<(DeclarationId, Vec<TypeId>)>,
+    // declaration_id of every non-generic function whose body has been walked
+    walked: HashSet<DeclarationId>,
+    // (declaration_id, description) of the functions whose bodies are being
+    // walked, innermost last
+    stack: Vec<(DeclarationId, String)>,
+}
+
+impl Instances {
+    /// The instances of the generic function `original_id`, in the order their
+    /// copies were added to the declaration engine.
+    pub(crate) fn functions_of(
+        &self,
+        session: &Session,
+        original_id: DeclId<TyFunctionDeclaration>,
+    ) -> Result<Vec<Arc<TyFunctionDeclaration>>, String> {
+        self.copies_of(session, original_id)?
+            .into_iter()
+            .map(|copy_id| de_get_function(session, copy_id))
+            .collect()
+    }
+
+    /// The instances of the generic struct `original_id`, in the order their
+    /// copies were added to the declaration engine.
+    pub(crate) fn structs_of(
+        &self,
+        session: &Session,
+        original_id: DeclId<TyStructDeclaration>,
+    ) -> Result<Vec<Arc<TyStructDeclaration>>, String> {
+        self.copies_of(session, original_id)?
+            .into_iter()
+            .map(|copy_id| de_get_struct(session, copy_id))
+            .collect()
+    }
+
+    /// The instantiations of the generic declaration `original_id` that are
+    /// instances, with the type arguments and the requesting nodes of each, in
+    /// the order their copies were added to the declaration engine.
+    pub(crate) fn instantiations_of<T>(
+        &self,
+        session: &Session,
+        original_id: DeclId<T>,
+    ) -> Vec<Instantiation>
+    where
+        T: Declaration,
+    {
+        de_get_instantiations(session, original_id)
+            .into_iter()
+            .filter(|instantiation| self.copies.contains(&instantiation.copy_id))
+            .collect()
+    }
+
+    fn copies_of<T>(
+        &self,
+        session: &Session,
+        original_id: DeclId<T>,
+    ) -> Result<Vec<DeclId<T>>, String>
+    where
+        T: Declaration,
+    {
+        self.instantiations_of(session, original_id)
+            .into_iter()
+            .map(|instantiation| de_expect_decl_id(session, instantiation.copy_id))
+            .collect()
+    }
+}
+
+/// Collects the instances that are reachable from the `main` function of every
+/// file of the application.
+pub(crate) fn collect_instances(qe: &mut QueryEngine) -> Result<Instances, String> {
+    let mut instances = Instances::default();
+    for file in qe.application().inner_ref().files.iter() {
+        for node in file.inner_ref().nodes.iter() {
+            let decl = match node.inner_ref() {
+                TyNode::Declaration(decl) => decl,
+                _ => continue,
+            };
+            if let TyDeclaration::Function(decl_id) = decl.inner_ref() {
+                let func_decl = de_get_function(qe.session(), *decl_id.inner_ref())?;
+                if func_decl.name == "main" && func_decl.type_parameters.is_empty() {
+                    collect_function_use(qe, &mut instances, decl_id, decl.idx(), &[], None)?;
+                }
+            }
+        }
+    }
+    Ok(instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
+        language::{
+            parsed::{
+                constructors::*, declaration::constructors::*, expression::constructors::*,
+                Application, File,
+            },
+            ty::TyApplication,
+        },
+        query_engine::query_cache::QueryCache,
+        semantic_analysis::graph_collection::collect_graph,
+        type_system::type_info::constructors::*,
+    };
+
+    /// An application whose `main` uses the generic function `F` at three call
+    /// sites, twice with `u32` and once with `u64`, after graph collection.
+    fn three_uses_of_f(session: &Session) -> (CollectionContext, CCIdx<TyApplication>) {
+        let f_fn = func_decl(
+            "F",
+            &[type_param("T", None)],
+            &[func_param("a", t_gen_("T"))],
+            &[return_(var("a"))],
+            t_gen_("T"),
+        );
+        let main_fn = func_decl(
+            "main",
+            &[],
+            &[],
+            &[
+                var_decl("x", Some(t_u32()), func_app("F", &[], &[u32(1u32)])),
+                var_decl("y", Some(t_u32()), func_app("F", &[], &[u32(2u32)])),
+                var_decl("z", Some(t_u64()), func_app("F", &[], &[u64(3u64)])),
+            ],
+            t_unit(),
+        );
+        let application = Application {
+            files: vec![File {
+                name: "bob.sw".to_string(),
+                nodes: vec![f_fn, main_fn],
+            }],
+        };
+        let mut cc = CollectionContext::default();
+        let application = collect_graph(session, &mut cc, application).unwrap();
+        (cc, application)
+    }
+
+    fn function_named(
+        session: &Session,
+        application: &CCIdx<TyApplication>,
+        name: &str,
+    ) -> DeclId<TyFunctionDeclaration> {
+        application.inner_ref().files[0]
+            .inner_ref()
+            .nodes
+            .iter()
+            .find_map(|node| match node.inner_ref() {
+                TyNode::Declaration(decl) => match decl.inner_ref() {
+                    TyDeclaration::Function(decl_id)
+                        if de_get_function(session, *decl_id.inner_ref()).unwrap().name == name =>
+                    {
+                        Some(*decl_id.inner_ref())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn one_copy_per_type_arguments_test() {
+        let session = Session::new();
+        let (cc, application) = three_uses_of_f(&session);
+        let f_id = function_named(&session, &application, "F");
+
+        // instances are described by their type arguments, which are displayed
+        // through the active session
+        session.enter(|| {
+            let mut cache = QueryCache::default();
+            let mut qe = QueryEngine::new(&session, &cc, &application, &mut cache);
+            qe.type_check_application().unwrap();
+            let before = de_get_instantiations(&session, f_id).len();
+            let instances = collect_instances(&mut qe).unwrap();
+
+            // three uses with two distinct type arguments make two copies
+            assert_eq!(de_get_instantiations(&session, f_id).len(), before + 2);
+            assert_eq!(instances.functions_of(&session, f_id).unwrap().len(), 2);
+        });
+    }
+
+    #[test]
+    fn requested_by_every_use_test() {
+        let session = Session::new();
+        let (cc, application) = three_uses_of_f(&session);
+        let f_id = function_named(&session, &application, "F");
+        let main_id = function_named(&session, &application, "main");
+        let uses = de_get_function(&session, main_id)
+            .unwrap()
+            .body
+            .inner_ref()
+            .contents
+            .iter()
+            .map(|node| node.idx())
+            .collect::<Vec<_>>();
+
+        session.enter(|| {
+            let mut cache = QueryCache::default();
+            let mut qe = QueryEngine::new(&session, &cc, &application, &mut cache);
+            qe.type_check_application().unwrap();
+            let instances = collect_instances(&mut qe).unwrap();
+
+            let instantiations = instances.instantiations_of(&session, f_id);
+            let summary = instantiations
+                .iter()
+                .map(|instantiation| {
+                    (
+                        instantiation.original_id,
+                        instantiation
+                            .type_arguments
+                            .iter()
+                            .map(|type_argument| type_argument.to_string())
+                            .collect::<Vec<_>>(),
+                        instantiation.requested_by.clone(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                summary,
+                vec![
+                    (
+                        f_id.untyped(),
+                        vec!["u32".to_string()],
+                        vec![uses[0], uses[1]]
+                    ),
+                    (f_id.untyped(), vec!["u64".to_string()], vec![uses[2]]),
+                ]
+            );
+        });
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod graph_collection;
+pub(crate) mod instance_collection;
 pub(crate) mod ty_to_resolved;
 pub(crate) mod type_collection;
 pub(crate) mod type_inference;
@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use crate::{
     collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
-    declaration_engine::{declaration_engine::*, declaration_id::DeclarationId},
+    declaration_engine::{declaration_engine::*, declaration_id::DeclId},
     language::{
         resolved::resolved_declaration::{
             ResolvedCodeBlock, ResolvedDeclaration, ResolvedFunctionDeclaration,
@@ -10,9 +12,13 @@ use crate::{
         },
         ty::typed_declaration::{
             TyCodeBlock, TyDeclaration, TyFunctionDeclaration, TyFunctionParameter,
-            TyStructDeclaration, TyStructField, TyVariableDeclaration,
+            TyStructDeclaration, TyStructField, TyTraitDeclaration, TyTraitFn, TyTraitImpl,
+            TyVariableDeclaration,
         },
     },
+    mangling::{mangle, mangle_struct, PathSegment},
+    semantic_analysis::instance_collection::Instances,
+    session::Session,
     type_system::{
         resolved_types::ResolvedTypeParameter, type_engine::resolve_type,
         type_parameter::TypeParameter,
@@ -22,75 +28,98 @@ use crate::{
 use super::{expression::to_resolved_expression, to_resolved_node};
 
 pub(super) fn to_resolved_declaration(
+    session: &Session,
     cc: &CollectionContext,
+    instances: &Instances,
+    path: &[PathSegment],
     decl: CCIdx<TyDeclaration>,
-) -> Vec<ResolvedDeclaration> {
+) -> Result<Vec<ResolvedDeclaration>, String> {
     match decl.inner() {
         TyDeclaration::Variable(var_decl) => {
-            let var_decl = to_resolved_variable_declaration(var_decl);
-            vec![ResolvedDeclaration::Variable(var_decl)]
+            let var_decl = to_resolved_variable_declaration(session, var_decl)?;
+            Ok(vec![ResolvedDeclaration::Variable(var_decl)])
         }
         TyDeclaration::Function(decl_id) => {
-            let func_decls = to_resolved_function_declaration(cc, decl_id);
-            func_decls
+            let func_decls =
+                to_resolved_function_declaration(session, cc, instances, path, decl_id)?;
+            Ok(func_decls
                 .into_iter()
                 .map(ResolvedDeclaration::Function)
-                .collect()
+                .collect())
         }
         TyDeclaration::Trait(decl_id) => {
-            let trait_decl = to_resolved_trait_declaration(decl_id);
-            vec![ResolvedDeclaration::Trait(trait_decl)]
+            let trait_decl = to_resolved_trait_declaration(session, path, decl_id)?;
+            Ok(vec![ResolvedDeclaration::Trait(trait_decl)])
         }
         TyDeclaration::TraitImpl(decl_id) => {
-            let trait_impl = to_resolved_trait_impl(cc, decl_id);
-            vec![ResolvedDeclaration::TraitImpl(trait_impl)]
+            let trait_impl = to_resolved_trait_impl(session, cc, instances, path, decl_id)?;
+            Ok(vec![ResolvedDeclaration::TraitImpl(trait_impl)])
         }
         TyDeclaration::Struct(decl_id) => {
-            let struct_decls = to_resolved_struct_declaration(decl_id);
-            struct_decls
+            let struct_decls = to_resolved_struct_declaration(session, instances, decl_id)?;
+            Ok(struct_decls
                 .into_iter()
                 .map(ResolvedDeclaration::Struct)
-                .collect()
+                .collect())
         }
     }
 }
 
 fn to_resolved_variable_declaration(
+    session: &Session,
     variable_declaration: TyVariableDeclaration,
-) -> ResolvedVariableDeclaration {
-    let type_ascription = resolve_type(variable_declaration.type_ascription).unwrap();
-    let body = to_resolved_expression(variable_declaration.body);
-    ResolvedVariableDeclaration {
+) -> Result<ResolvedVariableDeclaration, String> {
+    let type_ascription = resolve_type(session, variable_declaration.type_ascription)?;
+    let body = to_resolved_expression(session, variable_declaration.body)?;
+    Ok(ResolvedVariableDeclaration {
         name: variable_declaration.name,
         type_ascription,
         body,
-    }
+    })
 }
 
 fn to_resolved_function_declaration(
+    session: &Session,
     cc: &CollectionContext,
-    function_id: CCIdx<DeclarationId>,
-) -> Vec<ResolvedFunctionDeclaration> {
+    instances: &Instances,
+    path: &[PathSegment],
+    function_id: CCIdx<DeclId<TyFunctionDeclaration>>,
+) -> Result<Vec<ResolvedFunctionDeclaration>, String> {
     let function_id = function_id.inner();
-    let original_copy = de_get_function(function_id).unwrap();
+    let original_copy = de_get_function(session, function_id)?;
     if original_copy.type_parameters.is_empty() {
-        to_resolved_function_declaration_inner(cc, vec![original_copy])
+        to_resolved_function_declaration_inner(session, cc, instances, path, vec![original_copy])
     } else {
-        let monomorphized_copies = de_get_monomorphized_function_copies(function_id).unwrap();
-        to_resolved_function_declaration_inner(cc, monomorphized_copies)
+        let monomorphized_copies = instances.functions_of(session, function_id)?;
+        to_resolved_function_declaration_inner(session, cc, instances, path, monomorphized_copies)
     }
 }
 
 fn to_resolved_function_declaration_inner(
+    session: &Session,
     cc: &CollectionContext,
-    func_decls: Vec<TyFunctionDeclaration>,
-) -> Vec<ResolvedFunctionDeclaration> {
+    instances: &Instances,
+    path: &[PathSegment],
+    func_decls: Vec<Arc<TyFunctionDeclaration>>,
+) -> Result<Vec<ResolvedFunctionDeclaration>, String> {
     let mut new_func_decls = vec![];
     for func_decl in func_decls.into_iter() {
+        // the declaration is shared with the declaration engine, and lowering
+        // it takes it apart
+        let func_decl = (*func_decl).clone();
+        let mut func_path = path.to_vec();
+        func_path.push(PathSegment::Item {
+            name: func_decl.name.clone(),
+            type_arguments: func_decl
+                .type_parameters
+                .iter()
+                .map(|type_parameter| type_parameter.type_id)
+                .collect(),
+        });
         let resolved_type_parameters = func_decl
             .type_parameters
             .into_iter()
-            .map(resolve_type_parameter)
+            .map(|x| resolve_type_parameter(session, x))
             .collect::<Result<_, _>>();
         let resolved_type_parameters = match resolved_type_parameters {
             Ok(resolved_type_parameters) => resolved_type_parameters,
@@ -103,12 +132,14 @@ fn to_resolved_function_declaration_inner(
         let resolved_parameters = func_decl
             .parameters
             .into_iter()
-            .map(to_resolved_function_parameter)
-            .collect::<Vec<_>>();
-        let resolved_body = to_resolved_code_block(cc, func_decl.body);
-        let resolved_type = resolve_type(func_decl.return_type).unwrap();
+            .map(|x| to_resolved_function_parameter(session, x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resolved_body =
+            to_resolved_code_block(session, cc, instances, &func_path, func_decl.body)?;
+        let resolved_type = resolve_type(session, func_decl.return_type)?;
         let func_decl = ResolvedFunctionDeclaration {
             name: func_decl.name,
+            mangled_name: mangle(session, &func_path)?,
             type_parameters: resolved_type_parameters,
             parameters: resolved_parameters,
             body: resolved_body,
@@ -116,109 +147,142 @@ fn to_resolved_function_declaration_inner(
         };
         new_func_decls.push(func_decl);
     }
-    new_func_decls
+    Ok(new_func_decls)
 }
 
-fn resolve_type_parameter(type_parameter: TypeParameter) -> Result<ResolvedTypeParameter, String> {
+fn resolve_type_parameter(
+    session: &Session,
+    type_parameter: TypeParameter,
+) -> Result<ResolvedTypeParameter, String> {
     let type_param = ResolvedTypeParameter {
-        type_info: resolve_type(type_parameter.type_id)?,
+        type_info: resolve_type(session, type_parameter.type_id)?,
     };
     Ok(type_param)
 }
 
 fn to_resolved_function_parameter(
+    session: &Session,
     function_parameter: TyFunctionParameter,
-) -> ResolvedFunctionParameter {
-    ResolvedFunctionParameter {
+) -> Result<ResolvedFunctionParameter, String> {
+    Ok(ResolvedFunctionParameter {
         name: function_parameter.name,
-        type_info: resolve_type(function_parameter.type_id).unwrap(),
-    }
+        type_info: resolve_type(session, function_parameter.type_id)?,
+    })
 }
 
 fn to_resolved_code_block(
+    session: &Session,
     cc: &CollectionContext,
+    instances: &Instances,
+    path: &[PathSegment],
     code_block: CCIdx<TyCodeBlock>,
-) -> ResolvedCodeBlock {
-    let nodes = code_block
-        .inner()
-        .contents
-        .into_iter()
-        .flat_map(|node| to_resolved_node(cc, node))
-        .collect::<Vec<_>>();
-    ResolvedCodeBlock { contents: nodes }
+) -> Result<ResolvedCodeBlock, String> {
+    let mut nodes = vec![];
+    for node in code_block.inner().contents.into_iter() {
+        nodes.append(&mut to_resolved_node(session, cc, instances, path, node)?);
+    }
+    Ok(ResolvedCodeBlock { contents: nodes })
 }
 
-fn to_resolved_trait_declaration(trait_id: CCIdx<DeclarationId>) -> ResolvedTraitDeclaration {
+fn to_resolved_trait_declaration(
+    session: &Session,
+    path: &[PathSegment],
+    trait_id: CCIdx<DeclId<TyTraitDeclaration>>,
+) -> Result<ResolvedTraitDeclaration, String> {
     let trait_id = trait_id.inner();
-    let trait_decl = de_get_trait(trait_id).unwrap();
+    let trait_decl = de_get_trait(session, trait_id)?;
     let new_interface_surface = trait_decl
         .interface_surface
-        .into_iter()
-        .map(to_resolved_trait_fn)
-        .collect::<Vec<_>>();
-    ResolvedTraitDeclaration {
-        name: trait_decl.name,
+        .iter()
+        .map(|x| to_resolved_trait_fn(session, x))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut trait_path = path.to_vec();
+    trait_path.push(PathSegment::named(&trait_decl.name));
+    Ok(ResolvedTraitDeclaration {
+        mangled_name: mangle(session, &trait_path)?,
+        name: trait_decl.name.clone(),
         interface_surface: new_interface_surface,
-    }
+    })
 }
 
-fn to_resolved_trait_fn(trait_fn_id: CCIdx<DeclarationId>) -> ResolvedTraitFn {
-    let trait_fn_id = trait_fn_id.inner();
-    let trait_fn = de_get_trait_fn(trait_fn_id).unwrap();
+fn to_resolved_trait_fn(
+    session: &Session,
+    trait_fn_id: &CCIdx<DeclId<TyTraitFn>>,
+) -> Result<ResolvedTraitFn, String> {
+    let trait_fn = de_get_trait_fn(session, *trait_fn_id.inner_ref())?;
     let resolved_parameters = trait_fn
         .parameters
-        .into_iter()
-        .map(to_resolved_function_parameter)
-        .collect::<Vec<_>>();
-    let resolved_type = resolve_type(trait_fn.return_type).unwrap();
-    ResolvedTraitFn {
-        name: trait_fn.name,
+        .iter()
+        .cloned()
+        .map(|x| to_resolved_function_parameter(session, x))
+        .collect::<Result<Vec<_>, _>>()?;
+    let resolved_type = resolve_type(session, trait_fn.return_type)?;
+    Ok(ResolvedTraitFn {
+        name: trait_fn.name.clone(),
         parameters: resolved_parameters,
         return_type: resolved_type,
-    }
+    })
 }
 
 fn to_resolved_trait_impl(
+    session: &Session,
     cc: &CollectionContext,
-    impl_id: CCIdx<DeclarationId>,
-) -> ResolvedTraitImpl {
+    instances: &Instances,
+    path: &[PathSegment],
+    impl_id: CCIdx<DeclId<TyTraitImpl>>,
+) -> Result<ResolvedTraitImpl, String> {
     let impl_id = impl_id.inner();
-    let trait_impl = de_get_trait_impl(impl_id).unwrap();
-    let type_implementing_for = resolve_type(trait_impl.type_implementing_for).unwrap();
-    let methods = trait_impl
-        .methods
-        .into_iter()
-        .flat_map(|method| to_resolved_function_declaration(cc, method))
-        .collect::<Vec<_>>();
-    ResolvedTraitImpl {
-        trait_name: trait_impl.trait_name,
+    let trait_impl = de_get_trait_impl(session, impl_id)?;
+    let type_implementing_for = resolve_type(session, trait_impl.type_implementing_for)?;
+    let mut impl_path = path.to_vec();
+    impl_path.push(PathSegment::Impl {
+        trait_name: trait_impl.trait_name.clone(),
+        type_implementing_for: trait_impl.type_implementing_for,
+    });
+    let mut methods = vec![];
+    for method in trait_impl.methods.iter() {
+        methods.append(&mut to_resolved_function_declaration(
+            session,
+            cc,
+            instances,
+            &impl_path,
+            method.clone(),
+        )?);
+    }
+    Ok(ResolvedTraitImpl {
+        trait_name: trait_impl.trait_name.clone(),
         type_implementing_for,
         methods,
-    }
+    })
 }
 
 fn to_resolved_struct_declaration(
-    struct_id: CCIdx<DeclarationId>,
-) -> Vec<ResolvedStructDeclaration> {
+    session: &Session,
+    instances: &Instances,
+    struct_id: CCIdx<DeclId<TyStructDeclaration>>,
+) -> Result<Vec<ResolvedStructDeclaration>, String> {
     let struct_id = struct_id.inner();
-    let original_copy = de_get_struct(struct_id).unwrap();
+    let original_copy = de_get_struct(session, struct_id)?;
     if original_copy.type_parameters.is_empty() {
-        to_resolved_struct_declaration_inner(vec![original_copy])
+        to_resolved_struct_declaration_inner(session, vec![original_copy])
     } else {
-        let monomorphized_copies = de_get_monomorphized_struct_copies(struct_id).unwrap();
-        to_resolved_struct_declaration_inner(monomorphized_copies)
+        let monomorphized_copies = instances.structs_of(session, struct_id)?;
+        to_resolved_struct_declaration_inner(session, monomorphized_copies)
     }
 }
 
 fn to_resolved_struct_declaration_inner(
-    struct_decls: Vec<TyStructDeclaration>,
-) -> Vec<ResolvedStructDeclaration> {
+    session: &Session,
+    struct_decls: Vec<Arc<TyStructDeclaration>>,
+) -> Result<Vec<ResolvedStructDeclaration>, String> {
     let mut new_struct_decls = vec![];
     for struct_decl in struct_decls.into_iter() {
+        let struct_decl = (*struct_decl).clone();
         let resolved_type_parameters = struct_decl
             .type_parameters
-            .into_iter()
-            .map(resolve_type_parameter)
+            .iter()
+            .cloned()
+            .map(|x| resolve_type_parameter(session, x))
             .collect::<Result<_, _>>();
         let resolved_type_parameters = match resolved_type_parameters {
             Ok(resolved_type_parameters) => resolved_type_parameters,
@@ -230,8 +294,9 @@ fn to_resolved_struct_declaration_inner(
         };
         let resolved_fields = struct_decl
             .fields
-            .into_iter()
-            .map(to_resolved_struct_field)
+            .iter()
+            .cloned()
+            .map(|x| to_resolved_struct_field(session, x))
             .collect::<Result<_, _>>();
         let resolved_fields = match resolved_fields {
             Ok(resolved_fields) => resolved_fields,
@@ -242,19 +307,23 @@ fn to_resolved_struct_declaration_inner(
             }
         };
         let struct_decl = ResolvedStructDeclaration {
+            mangled_name: mangle_struct(session, &struct_decl)?,
             name: struct_decl.name,
             type_parameters: resolved_type_parameters,
             fields: resolved_fields,
         };
         new_struct_decls.push(struct_decl);
     }
-    new_struct_decls
+    Ok(new_struct_decls)
 }
 
-fn to_resolved_struct_field(field: TyStructField) -> Result<ResolvedStructField, String> {
+fn to_resolved_struct_field(
+    session: &Session,
+    field: TyStructField,
+) -> Result<ResolvedStructField, String> {
     let field = ResolvedStructField {
         name: field.name,
-        type_info: resolve_type(field.type_id)?,
+        type_info: resolve_type(session, field.type_id)?,
     };
     Ok(field)
 }
@@ -5,19 +5,26 @@ use crate::{
         },
         ty::typed_expression::{TyExpression, TyExpressionVariant, TyStructExpressionField},
     },
+    session::Session,
     type_system::type_engine::resolve_type,
 };
 
-pub(super) fn to_resolved_expression(expression: TyExpression) -> ResolvedExpression {
-    let variant = resolve_expression_variant(expression.variant);
-    ResolvedExpression {
+pub(super) fn to_resolved_expression(
+    session: &Session,
+    expression: TyExpression,
+) -> Result<ResolvedExpression, String> {
+    let variant = resolve_expression_variant(session, expression.variant)?;
+    Ok(ResolvedExpression {
         variant,
-        type_info: resolve_type(expression.type_id).unwrap(),
-    }
+        type_info: resolve_type(session, expression.type_id)?,
+    })
 }
 
-fn resolve_expression_variant(variant: TyExpressionVariant) -> ResolvedExpressionVariant {
-    match variant {
+fn resolve_expression_variant(
+    session: &Session,
+    variant: TyExpressionVariant,
+) -> Result<ResolvedExpressionVariant, String> {
+    let variant = match variant {
         TyExpressionVariant::Literal { value } => ResolvedExpressionVariant::Literal { value },
         TyExpressionVariant::Variable { name } => ResolvedExpressionVariant::Variable { name },
         TyExpressionVariant::FunctionApplication {
@@ -27,8 +34,8 @@ fn resolve_expression_variant(variant: TyExpressionVariant) -> ResolvedExpressio
         } => {
             let resolved_arguments = arguments
                 .into_iter()
-                .map(to_resolved_expression)
-                .collect::<Vec<_>>();
+                .map(|x| to_resolved_expression(session, x))
+                .collect::<Result<Vec<_>, _>>()?;
             ResolvedExpressionVariant::FunctionApplication {
                 name,
                 arguments: resolved_arguments,
@@ -41,8 +48,8 @@ fn resolve_expression_variant(variant: TyExpressionVariant) -> ResolvedExpressio
         } => {
             let resolved_fields = fields
                 .into_iter()
-                .map(resolve_struct_expression_field)
-                .collect::<Vec<_>>();
+                .map(|x| resolve_struct_expression_field(session, x))
+                .collect::<Result<Vec<_>, _>>()?;
             ResolvedExpressionVariant::Struct {
                 struct_name,
                 fields: resolved_fields,
@@ -56,8 +63,8 @@ fn resolve_expression_variant(variant: TyExpressionVariant) -> ResolvedExpressio
         } => {
             let resolved_arguments = arguments
                 .into_iter()
-                .map(to_resolved_expression)
-                .collect::<Vec<_>>();
+                .map(|x| to_resolved_expression(session, x))
+                .collect::<Result<Vec<_>, _>>()?;
             ResolvedExpressionVariant::MethodCall {
                 parent_name,
                 func_name,
@@ -67,15 +74,17 @@ fn resolve_expression_variant(variant: TyExpressionVariant) -> ResolvedExpressio
         TyExpressionVariant::FunctionParameter => {
             panic!("did not expect to find function param here")
         }
-    }
+    };
+    Ok(variant)
 }
 
 fn resolve_struct_expression_field(
+    session: &Session,
     struct_expression_field: TyStructExpressionField,
-) -> ResolvedStructExpressionField {
-    let new_value = to_resolved_expression(struct_expression_field.value);
-    ResolvedStructExpressionField {
+) -> Result<ResolvedStructExpressionField, String> {
+    let new_value = to_resolved_expression(session, struct_expression_field.value)?;
+    Ok(ResolvedStructExpressionField {
         name: struct_expression_field.name,
         value: new_value,
-    }
+    })
 }
@@ -6,56 +6,34 @@ use expression::*;
 
 use crate::{
     collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
-    language::{
-        resolved::{ResolvedApplication, ResolvedFile, ResolvedNode},
-        ty::{TyApplication, TyFile, TyNode},
-    },
+    language::{resolved::ResolvedNode, ty::TyNode},
+    mangling::PathSegment,
+    semantic_analysis::instance_collection::Instances,
+    session::Session,
 };
 
-pub(crate) fn to_resolved(
+/// Resolves `node`, which is found at `path`.
+pub(crate) fn to_resolved_node(
+    session: &Session,
     cc: &CollectionContext,
-    application: CCIdx<TyApplication>,
-) -> ResolvedApplication {
-    let files = application
-        .inner()
-        .files
-        .into_iter()
-        .map(|file| to_resolved_file(cc, file))
-        .collect();
-    ResolvedApplication { files }
-}
-
-fn to_resolved_file(cc: &CollectionContext, file: CCIdx<TyFile>) -> ResolvedFile {
-    let TyFile { name, nodes } = file.inner();
-    let new_nodes = to_resolved_nodes(cc, nodes);
-    ResolvedFile {
-        name,
-        nodes: new_nodes,
-    }
-}
-
-fn to_resolved_nodes(cc: &CollectionContext, nodes: Vec<CCIdx<TyNode>>) -> Vec<ResolvedNode> {
-    nodes
-        .into_iter()
-        .flat_map(|node| to_resolved_node(cc, node))
-        .collect()
-}
-
-fn to_resolved_node(cc: &CollectionContext, node: CCIdx<TyNode>) -> Vec<ResolvedNode> {
+    instances: &Instances,
+    path: &[PathSegment],
+    node: CCIdx<TyNode>,
+) -> Result<Vec<ResolvedNode>, String> {
     match node.inner() {
         TyNode::Declaration(decl) => {
-            let declarations = to_resolved_declaration(cc, decl);
-            declarations
+            let declarations = to_resolved_declaration(session, cc, instances, path, decl)?;
+            Ok(declarations
                 .into_iter()
                 .map(ResolvedNode::Declaration)
-                .collect()
-        }
-        TyNode::Expression(exp) => {
-            vec![ResolvedNode::Expression(to_resolved_expression(exp))]
-        }
-        TyNode::ReturnStatement(exp) => {
-            vec![ResolvedNode::ReturnStatement(to_resolved_expression(exp))]
+                .collect())
         }
-        TyNode::StarImport(filename) => vec![ResolvedNode::StarImport(filename)],
+        TyNode::Expression(exp) => Ok(vec![ResolvedNode::Expression(to_resolved_expression(
+            session, exp,
+        )?)]),
+        TyNode::ReturnStatement(exp) => Ok(vec![ResolvedNode::ReturnStatement(
+            to_resolved_expression(session, exp)?,
+        )]),
+        TyNode::StarImport(filename) => Ok(vec![ResolvedNode::StarImport(filename)]),
     }
 }
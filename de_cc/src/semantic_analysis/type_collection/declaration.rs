@@ -3,105 +3,113 @@ use crate::{
     declaration_engine::{
         declaration_engine::{
             de_get_function, de_get_struct, de_get_trait, de_get_trait_fn, de_get_trait_impl,
+            de_replace_function, de_replace_struct, de_replace_trait_fn, de_replace_trait_impl,
         },
-        declaration_id::DeclarationId,
+        declaration_id::DeclId,
     },
-    language::ty::typed_declaration::TyDeclaration,
-    type_system::{type_engine::resolve_custom_types, type_mapping::insert_type_parameters},
-    types::copy_types::CopyTypes,
+    language::ty::typed_declaration::{
+        TyFunctionDeclaration, TyStructDeclaration, TyTraitFn, TyTraitImpl,
+    },
+    session::Session,
+    type_system::type_engine::resolve_custom_types,
 };
 
-pub(super) fn collect_types_declaration(cc: &CollectionContext, decl: &mut CCIdx<TyDeclaration>) {
-    match decl.inner_ref_mut() {
-        TyDeclaration::Variable(_) => {}
-        TyDeclaration::Function(decl_id) => collect_types_function(cc, decl_id),
-        TyDeclaration::Trait(decl_id) => collect_types_trait(cc, decl_id),
-        TyDeclaration::TraitImpl(decl_id) => collect_types_trait_impl(cc, decl_id),
-        TyDeclaration::Struct(decl_id) => collect_types_struct(cc, decl_id),
-    }
-}
-
-fn collect_types_function(cc: &CollectionContext, decl_id: &mut CCIdx<DeclarationId>) {
-    let mut func_decl = de_get_function(*decl_id.inner_ref()).unwrap();
+pub(crate) fn collect_types_function(
+    session: &Session,
+    cc: &CollectionContext,
+    decl_id: &CCIdx<DeclId<TyFunctionDeclaration>>,
+) -> Result<(), String> {
+    let func_decl = (*de_get_function(session, *decl_id.inner_ref())?).clone();
+    let type_parameters = &func_decl.type_parameters;
 
-    // create type mapping
-    let type_mapping = insert_type_parameters(func_decl.type_parameters.clone());
-
-    // resolve any custom types in the parameters and
-    // insert the type parameters into the ns
-    for param in func_decl.parameters.iter_mut() {
-        resolve_custom_types(param.type_id, cc, decl_id.idx()).unwrap();
-        param.copy_types(&type_mapping);
+    // resolve any custom types in the parameters
+    for param in func_decl.parameters.iter() {
+        resolve_custom_types(session, param.type_id, cc, decl_id.idx(), type_parameters)?;
     }
 
     // resolve any custom types in the function return type
-    resolve_custom_types(func_decl.return_type, cc, decl_id.idx()).unwrap();
-    func_decl.return_type.copy_types(&type_mapping);
+    resolve_custom_types(
+        session,
+        func_decl.return_type,
+        cc,
+        decl_id.idx(),
+        type_parameters,
+    )?;
+
+    de_replace_function(session, *decl_id.inner_ref(), func_decl)
 }
 
-fn collect_types_trait_impl(cc: &CollectionContext, decl_id: &mut CCIdx<DeclarationId>) {
-    let mut trait_impl = de_get_trait_impl(*decl_id.inner_ref()).unwrap();
+pub(crate) fn collect_types_trait_impl(
+    session: &Session,
+    cc: &CollectionContext,
+    decl_id: &CCIdx<DeclId<TyTraitImpl>>,
+) -> Result<(), String> {
+    let trait_impl = (*de_get_trait_impl(session, *decl_id.inner_ref())?).clone();
 
     if !trait_impl.type_parameters.is_empty() {
-        panic!("no type parameters yet");
+        return Err("no type parameters yet".to_string());
     }
 
-    // create type mapping
-    let type_mapping = insert_type_parameters(trait_impl.type_parameters.clone());
-
     // get the trait from the declaration engine
     let trait_id = cc
         .get_symbol(decl_id.idx(), &trait_impl.trait_name)
-        .unwrap()
+        .and_then(|trait_id| trait_id.expect(session))?
         .inner();
-    let _trait_decl = de_get_trait(trait_id).unwrap();
+    let _trait_decl = de_get_trait(session, trait_id)?;
 
     // resolve any custom types in the type we are implementing for
-    resolve_custom_types(trait_impl.type_implementing_for, cc, decl_id.idx()).unwrap();
-    trait_impl.type_implementing_for.copy_types(&type_mapping);
+    resolve_custom_types(
+        session,
+        trait_impl.type_implementing_for,
+        cc,
+        decl_id.idx(),
+        &trait_impl.type_parameters,
+    )?;
 
     // TODO: check to see if all of the methods are implementing, no new methods implementing,
     // when generic traits are implemented add the monomorphized copies to the declaration
     // engine
 
-    // do type inference on the methods
-    trait_impl.methods.iter_mut().for_each(|method_id| {
-        collect_types_function(cc, method_id);
-        method_id.copy_types(&type_mapping);
-    });
-}
+    // the methods are collected separately, when their signatures are queried
 
-fn collect_types_struct(cc: &CollectionContext, decl_id: &mut CCIdx<DeclarationId>) {
-    let mut struct_decl = de_get_struct(*decl_id.inner_ref()).unwrap();
+    de_replace_trait_impl(session, *decl_id.inner_ref(), trait_impl)
+}
 
-    // create type mapping
-    let type_mapping = insert_type_parameters(struct_decl.type_parameters.clone());
+pub(crate) fn collect_types_struct(
+    session: &Session,
+    cc: &CollectionContext,
+    decl_id: &CCIdx<DeclId<TyStructDeclaration>>,
+) -> Result<(), String> {
+    let struct_decl = (*de_get_struct(session, *decl_id.inner_ref())?).clone();
 
     // do type inference on the fields
-    struct_decl.fields.iter_mut().for_each(|field| {
-        resolve_custom_types(field.type_id, cc, decl_id.idx()).unwrap();
-        field.copy_types(&type_mapping);
-    });
-}
-
-fn collect_types_trait(cc: &CollectionContext, decl_id: &mut CCIdx<DeclarationId>) {
-    let mut trait_decl = de_get_trait(*decl_id.inner_ref()).unwrap();
+    for field in struct_decl.fields.iter() {
+        resolve_custom_types(
+            session,
+            field.type_id,
+            cc,
+            decl_id.idx(),
+            &struct_decl.type_parameters,
+        )?;
+    }
 
-    // do type inference on the interface
-    trait_decl
-        .interface_surface
-        .iter_mut()
-        .for_each(|trait_fn_id| collect_types_trait_fn(cc, trait_fn_id));
+    de_replace_struct(session, *decl_id.inner_ref(), struct_decl)
 }
 
-fn collect_types_trait_fn(cc: &CollectionContext, decl_id: &mut CCIdx<DeclarationId>) {
-    let trait_fn = de_get_trait_fn(*decl_id.inner_ref()).unwrap();
+pub(crate) fn collect_types_trait_fn(
+    session: &Session,
+    cc: &CollectionContext,
+    decl_id: &CCIdx<DeclId<TyTraitFn>>,
+) -> Result<(), String> {
+    let trait_fn = (*de_get_trait_fn(session, *decl_id.inner_ref())?).clone();
 
     // resolve any custom types in the parameters
     for parameter in trait_fn.parameters.iter() {
-        resolve_custom_types(parameter.type_id, cc, decl_id.idx()).unwrap();
+        resolve_custom_types(session, parameter.type_id, cc, decl_id.idx(), &[])?;
     }
 
     // resolve any custom types in the return type
-    resolve_custom_types(trait_fn.return_type, cc, decl_id.idx()).unwrap();
+    resolve_custom_types(session, trait_fn.return_type, cc, decl_id.idx(), &[])?;
+
+    de_replace_trait_fn(session, *decl_id.inner_ref(), trait_fn)
 }
@@ -2,43 +2,127 @@
 //!
 //! Type collection includes:
 //! 1. visiting all types that touch intraprocedural objects
-//!     (struct/enum/function/trait/etc declarations)
+//!    (struct/enum/function/trait/etc declarations)
 //! 2. resolving custom types
 //! 3. applying CopyTypes to associate type parameters with generics
 //!
 //! Type collection does not include:
 //! - visiting types that do not touch intraprocedural objects
-//!     (function bodies are not visited)
+//!   (function bodies are not visited)
+//!
+//! Type collection is done one declaration at a time, as requested by the
+//! [QueryEngine](crate::query_engine::query_engine::QueryEngine).
 
 mod declaration;
 
-use declaration::*;
+pub(crate) use declaration::*;
 
-use crate::{
-    collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
-    language::ty::{TyApplication, TyFile, TyNode},
-};
+#[cfg(test)]
+mod tests {
+    use crate::{
+        collection_context::collection_context::CollectionContext,
+        declaration_engine::declaration_engine::*,
+        language::{
+            parsed::{
+                constructors::*, declaration::constructors::*, expression::constructors::*,
+                Application, File,
+            },
+            ty::{typed_declaration::TyDeclaration, TyNode},
+        },
+        query_engine::{query_cache::QueryCache, query_engine::QueryEngine},
+        semantic_analysis::graph_collection::collect_graph,
+        session::Session,
+        type_system::{
+            type_engine::look_up_type_id, type_id::TypeId, type_info::constructors::*,
+            type_info::TypeInfo,
+        },
+    };
 
-pub(crate) fn collect_types(cc: &CollectionContext, application: &mut CCIdx<TyApplication>) {
-    application
-        .inner_ref_mut()
-        .files
-        .iter_mut()
-        .for_each(|file| collect_types_file(cc, file));
-}
+    #[test]
+    fn refined_declarations_are_stored_test() {
+        let session = Session::new();
+        let data_decl = struct_("Data", &[], &[struct_field("x", t_u8())]);
+        let get_trait = trait_(
+            "Get",
+            &[trait_fn(
+                "get",
+                &[func_param("d", t_cus_("Data", &[]))],
+                t_u8(),
+            )],
+        );
+        let f_fn = func_decl(
+            "F",
+            &[],
+            &[func_param("d", t_cus_("Data", &[]))],
+            &[return_(var("d"))],
+            t_cus_("Data", &[]),
+        );
+        let main_fn = func_decl(
+            "main",
+            &[],
+            &[],
+            &[var_decl(
+                "x",
+                None,
+                func_app(
+                    "F",
+                    &[],
+                    &[struct_exp("Data", &[], &[struct_exp_field("x", u8(1u8))])],
+                ),
+            )],
+            t_unit(),
+        );
+        let application = Application {
+            files: vec![File {
+                name: "bob.sw".to_string(),
+                nodes: vec![data_decl, get_trait, f_fn, main_fn],
+            }],
+        };
+        let mut cc = CollectionContext::default();
+        let application = collect_graph(&session, &mut cc, application).unwrap();
 
-fn collect_types_file(cc: &CollectionContext, file: &mut CCIdx<TyFile>) {
-    file.inner_ref_mut()
-        .nodes
-        .iter_mut()
-        .for_each(|node| collect_types_node(cc, node));
-}
+        session.enter(|| {
+            let mut cache = QueryCache::default();
+            QueryEngine::new(&session, &cc, &application, &mut cache)
+                .type_check_application()
+                .unwrap();
+        });
 
-fn collect_types_node(cc: &CollectionContext, node: &mut CCIdx<TyNode>) {
-    match node.inner_ref_mut() {
-        TyNode::Declaration(decl) => collect_types_declaration(cc, decl),
-        TyNode::Expression(_) => {}
-        TyNode::ReturnStatement(_) => {}
-        TyNode::StarImport(_) => {}
+        // the declarations that later passes get from the declaration engine
+        // refer to the struct, rather than to its name
+        let is_data = |type_id: TypeId| {
+            matches!(
+                &*look_up_type_id(&session, type_id).unwrap(),
+                TypeInfo::Struct { name, .. } if name == "Data"
+            )
+        };
+        for node in application.inner_ref().files[0].inner_ref().nodes.iter() {
+            let decl = match node.inner_ref() {
+                TyNode::Declaration(decl) => decl,
+                _ => continue,
+            };
+            match decl.inner_ref() {
+                TyDeclaration::Trait(decl_id) => {
+                    let trait_decl = de_get_trait(&session, *decl_id.inner_ref()).unwrap();
+                    let trait_fn =
+                        de_get_trait_fn(&session, *trait_decl.interface_surface[0].inner_ref())
+                            .unwrap();
+                    assert!(is_data(trait_fn.parameters[0].type_id));
+                }
+                TyDeclaration::Function(decl_id) => {
+                    let func_decl = de_get_function(&session, *decl_id.inner_ref()).unwrap();
+                    if func_decl.name != "F" {
+                        continue;
+                    }
+                    assert!(is_data(func_decl.parameters[0].type_id));
+                    assert!(is_data(func_decl.return_type));
+                    match func_decl.body.inner_ref().contents[0].inner_ref() {
+                        TyNode::ReturnStatement(exp) => assert!(is_data(exp.type_id)),
+                        _ => panic!("expected a return statement"),
+                    }
+                }
+                _ => {}
+            }
+        }
     }
 }
@@ -1,10 +1,12 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::collection_context::collection_context::CollectionContext;
 use crate::collection_context::collection_index::CollectionIndex;
 use crate::declaration_engine::declaration_engine::*;
-use crate::type_system::type_engine::resolve_custom_types;
+use crate::query_engine::query_engine::QueryEngine;
+use crate::type_system::type_argument::TypeArgument;
+use crate::type_system::type_engine::{insert_type, resolve_custom_types};
 use crate::type_system::type_id::TypeId;
+use crate::type_system::type_info::TypeInfo;
 use crate::{
     language::ty::typed_expression::{TyExpression, TyExpressionVariant},
     namespace::namespace::Namespace,
@@ -12,27 +14,27 @@ use crate::{
     types::create_type_id::CreateTypeId,
 };
 
-pub(super) fn analyze_expression(
-    cc: &CollectionContext,
+pub(crate) fn analyze_expression(
+    qe: &mut QueryEngine,
     current_index: CollectionIndex,
     ns: &mut Namespace,
-    exp: &mut TyExpression,
-) {
-    analyze_expression_variant(cc, current_index, ns, &mut exp.variant, &mut exp.type_id);
+    exp: &TyExpression,
+) -> Result<(), String> {
+    analyze_expression_variant(qe, current_index, ns, &exp.variant, exp.type_id)
 }
 
 fn analyze_expression_variant(
-    cc: &CollectionContext,
+    qe: &mut QueryEngine,
     current_index: CollectionIndex,
     ns: &mut Namespace,
-    variant: &mut TyExpressionVariant,
-    type_id: &mut TypeId,
-) {
+    variant: &TyExpressionVariant,
+    type_id: TypeId,
+) -> Result<(), String> {
     match variant {
-        TyExpressionVariant::Literal { .. } => {}
+        TyExpressionVariant::Literal { .. } => Ok(()),
         TyExpressionVariant::Variable { name } => {
-            let variable_decl = ns.get_symbol(name).unwrap().expect_variable().unwrap();
-            unify_types(variable_decl.type_ascription, *type_id).unwrap();
+            let variable_decl = qe.cc().get_variable(current_index, name)?;
+            unify_types(qe.session(), variable_decl.type_ascription, type_id)
         }
         TyExpressionVariant::FunctionApplication {
             name,
@@ -41,45 +43,54 @@ fn analyze_expression_variant(
         } => {
             // don't allow type arguments in the prototype
             if !type_arguments.is_empty() {
-                panic!()
+                return Err("type arguments are not supported".to_string());
             }
 
             // get the original decl id for the function from the CC
-            let decl_id = cc.get_symbol(current_index, name).unwrap();
+            let decl_id = qe.cc().get_symbol(current_index, name)?;
 
-            // get the original function declaration
-            let mut typed_function_declaration = de_get_function(*decl_id.inner_ref()).unwrap();
+            // get the signature of the original function declaration
+            let signature = qe.signature_of(&decl_id)?;
 
             // make sure we have the correct number of arguments
-            if typed_function_declaration.parameters.len() != arguments.len() {
-                panic!();
+            if signature.parameters.len() != arguments.len() {
+                return Err(format!(
+                    "{} takes {} arguments but {} were given",
+                    name,
+                    signature.parameters.len(),
+                    arguments.len()
+                ));
             }
 
-            // do type inference on the type arguments
-            for type_argument in type_arguments.iter_mut() {
-                resolve_custom_types(type_argument.type_id, cc, current_index).unwrap();
-            }
-
-            // monomorphize the function declaration into a new copy, in place
-            monomorphize(&mut typed_function_declaration, type_arguments).unwrap();
+            // instantiate every type parameter with a new type variable
+            let type_arguments = signature
+                .type_parameters
+                .iter()
+                .map(|_| TypeArgument {
+                    type_id: insert_type(qe.session(), TypeInfo::Unknown),
+                })
+                .collect::<Vec<_>>();
 
-            // add the new copy to the declaration engine
-            de_add_monomorphized_function_copy(
-                *decl_id.inner_ref(),
-                typed_function_declaration.clone(),
-            );
+            // get the monomorphized copy of the function declaration
+            let decl_id = decl_id.expect(qe.session())?;
+            let copy_id = qe.monomorphize(&decl_id, &type_arguments, current_index)?;
+            let typed_function_declaration = de_get_function(qe.session(), copy_id)?;
 
             // do type inference on the arguments
-            arguments
-                .iter_mut()
+            for (argument, parameter) in arguments
+                .iter()
                 .zip(typed_function_declaration.parameters.iter())
-                .for_each(|(argument, parameter)| {
-                    analyze_expression(cc, current_index, ns, argument);
-                    unify_types(argument.type_id, parameter.type_id).unwrap();
-                });
+            {
+                analyze_expression(qe, current_index, ns, argument)?;
+                unify_types(qe.session(), argument.type_id, parameter.type_id)?;
+            }
 
             // unify the return type of the function declaration and the expression
-            unify_types(typed_function_declaration.return_type, *type_id).unwrap();
+            unify_types(
+                qe.session(),
+                typed_function_declaration.return_type,
+                type_id,
+            )
         }
         TyExpressionVariant::Struct {
             struct_name,
@@ -88,30 +99,46 @@ fn analyze_expression_variant(
         } => {
             // don't allow type arguments in the prototype
             if !type_arguments.is_empty() {
-                panic!()
+                return Err("type arguments are not supported".to_string());
             }
 
-            // get the original decl id for the struct from the cc
-            let decl_id = cc.get_symbol(current_index, struct_name).unwrap().inner();
+            // get the original decl id for the struct from the cc, and do type
+            // collection on it
+            let decl_id = qe.cc().get_symbol(current_index, struct_name)?;
+            qe.type_of_decl_id(&decl_id)?;
+            let decl_id = decl_id.expect(qe.session())?.inner();
 
             // get the original struct declaration
-            let mut typed_struct_declaration = de_get_struct(decl_id).unwrap();
+            let mut typed_struct_declaration = (*de_get_struct(qe.session(), decl_id)?).clone();
 
             // do type inference on the type arguments
-            for type_argument in type_arguments.iter_mut() {
-                resolve_custom_types(type_argument.type_id, cc, current_index).unwrap();
+            for type_argument in type_arguments.iter() {
+                resolve_custom_types(
+                    qe.session(),
+                    type_argument.type_id,
+                    qe.cc(),
+                    current_index,
+                    ns.type_parameters(),
+                )?;
             }
 
             // monomorphize the struct declaration into a new copy, in place
-            monomorphize(&mut typed_struct_declaration, type_arguments).unwrap();
-
-            // add the new copy to the declaration engine
-            de_add_monomorphized_struct_copy(decl_id, typed_struct_declaration.clone());
+            monomorphize(qe.session(), &mut typed_struct_declaration, type_arguments)?;
+
+            // add the new copy to the declaration engine, or reuse the copy for
+            // the same type arguments
+            let copy_id = de_add_monomorphized_struct_copy(
+                qe.session(),
+                decl_id,
+                current_index,
+                typed_struct_declaration,
+            )?;
+            let typed_struct_declaration = de_get_struct(qe.session(), copy_id)?;
 
             // create reference maps for the expression and the declaration
-            let mut given_fields_map: HashMap<_, _> = fields
+            let given_fields_map: HashMap<_, _> = fields
                 .iter()
-                .map(|field| (field.name.clone(), field.value.clone()))
+                .map(|field| (field.name.clone(), &field.value))
                 .collect();
             let oracle_fields_map: HashMap<_, _> = typed_struct_declaration
                 .fields
@@ -122,21 +149,25 @@ fn analyze_expression_variant(
 
             // check to see that all of the necessary fields are provided and that no erroneous
             // fields are provided
-            if given_fields_map.keys().into_iter().collect::<HashSet<_>>()
-                != oracle_fields_map.keys().into_iter().collect::<HashSet<_>>()
+            if given_fields_map.keys().collect::<HashSet<_>>()
+                != oracle_fields_map.keys().collect::<HashSet<_>>()
             {
-                panic!();
+                return Err(format!("wrong fields given for struct {}", struct_name));
             }
 
             // do type inference on the fields
-            given_fields_map.iter_mut().for_each(|(name, value)| {
-                analyze_expression(cc, current_index, ns, value);
+            for (name, value) in given_fields_map.iter() {
+                analyze_expression(qe, current_index, ns, value)?;
                 let oracle_field = oracle_fields_map.get(name).unwrap();
-                unify_types(value.type_id, *oracle_field).unwrap();
-            });
+                unify_types(qe.session(), value.type_id, *oracle_field)?;
+            }
 
             // unify the struct type id with the expression type id
-            unify_types(typed_struct_declaration.create_type_id(), *type_id).unwrap();
+            unify_types(
+                qe.session(),
+                typed_struct_declaration.create_type_id(qe.session(), decl_id),
+                type_id,
+            )
         }
         TyExpressionVariant::MethodCall {
             parent_name,
@@ -146,36 +177,40 @@ fn analyze_expression_variant(
         } => {
             // don't allow type arguments in the prototype
             if !type_arguments.is_empty() {
-                panic!()
+                return Err("type arguments are not supported".to_string());
             }
 
             // get the variable decl for this method call
-            let parent = ns
-                .get_symbol(parent_name)
-                .unwrap()
-                .expect_variable()
-                .unwrap();
+            let parent = qe.cc().get_variable(current_index, parent_name)?;
 
-            // get the method declaration
-            let typed_method_declaration =
-                ns.get_method(parent.type_ascription, func_name).unwrap();
+            // get the method declaration, either from a trait constraint in the ns or
+            // from a trait impl
+            let typed_method_declaration = ns
+                .get_method(qe.session(), parent.type_ascription, func_name)
+                .or_else(|_| qe.method_of(parent.type_ascription, func_name))?;
 
             // do type inference on the type arguments
-            type_arguments.iter().for_each(|type_arg| {
-                resolve_custom_types(type_arg.type_id, cc, current_index).unwrap()
-            });
+            for type_arg in type_arguments.iter() {
+                resolve_custom_types(
+                    qe.session(),
+                    type_arg.type_id,
+                    qe.cc(),
+                    current_index,
+                    ns.type_parameters(),
+                )?;
+            }
 
             // do type inference on the arguments
-            arguments
-                .iter_mut()
+            for (argument, parameter) in arguments
+                .iter()
                 .zip(typed_method_declaration.parameters.iter())
-                .for_each(|(argument, parameter)| {
-                    analyze_expression(cc, current_index, ns, argument);
-                    unify_types(argument.type_id, parameter.type_id).unwrap();
-                });
+            {
+                analyze_expression(qe, current_index, ns, argument)?;
+                unify_types(qe.session(), argument.type_id, parameter.type_id)?;
+            }
 
             // unify the return type of the method declaration and the expression
-            unify_types(typed_method_declaration.return_type, *type_id).unwrap();
+            unify_types(qe.session(), typed_method_declaration.return_type, type_id)
         }
         TyExpressionVariant::FunctionParameter => todo!(),
     }
@@ -4,44 +4,33 @@
 //! 1. visiting all function bodies and expressions
 //! 2. resolving custom types
 //! 3. monomorphizing as needed
+//!
+//! Type inference is done one declaration at a time, as requested by the
+//! [QueryEngine]. Any declaration that is used by the one being inferred---a
+//! called function, a struct, a method, etc---is inferred first by querying it
+//! in turn.
 
 mod declaration;
 mod expression;
 
-use declaration::*;
-use expression::*;
+pub(crate) use declaration::*;
+pub(crate) use expression::*;
 
 use crate::{
-    collection_context::{collection_context::CollectionContext, collection_index::CCIdx},
-    language::ty::{TyApplication, TyFile, TyNode},
-    namespace::namespace::Namespace,
+    collection_context::collection_index::CCIdx, language::ty::TyNode,
+    namespace::namespace::Namespace, query_engine::query_engine::QueryEngine,
 };
 
-pub(crate) fn analyze(
-    cc: &CollectionContext,
+fn analyze_node(
+    qe: &mut QueryEngine,
     ns: &mut Namespace,
-    application: &mut CCIdx<TyApplication>,
-) {
-    application
-        .inner_ref_mut()
-        .files
-        .iter_mut()
-        .for_each(|file| analyze_file(cc, ns, file));
-}
-
-fn analyze_file(cc: &CollectionContext, ns: &mut Namespace, file: &mut CCIdx<TyFile>) {
-    file.inner_ref_mut()
-        .nodes
-        .iter_mut()
-        .for_each(|node| analyze_node(cc, ns, node));
-}
-
-fn analyze_node(cc: &CollectionContext, ns: &mut Namespace, node: &mut CCIdx<TyNode>) {
+    node: &CCIdx<TyNode>,
+) -> Result<(), String> {
     let cc_idx = node.idx();
-    match node.inner_ref_mut() {
-        TyNode::Declaration(decl) => analyze_declaration(cc, ns, decl),
-        TyNode::Expression(expression) => analyze_expression(cc, cc_idx, ns, expression),
-        TyNode::ReturnStatement(expression) => analyze_expression(cc, cc_idx, ns, expression),
-        TyNode::StarImport(_) => {}
+    match node.inner_ref() {
+        TyNode::Declaration(decl) => analyze_declaration(qe, ns, decl),
+        TyNode::Expression(expression) => analyze_expression(qe, cc_idx, ns, expression),
+        TyNode::ReturnStatement(expression) => analyze_expression(qe, cc_idx, ns, expression),
+        TyNode::StarImport(_) => Ok(()),
     }
 }
@@ -0,0 +1,114 @@
+use std::cell::Cell;
+
+use crate::{
+    declaration_engine::declaration_engine::DeclarationEngine, type_system::type_engine::TypeEngine,
+};
+
+thread_local! {
+    /// The session that [TypeId](crate::type_system::type_id::TypeId) and
+    /// [DeclarationId](crate::declaration_engine::declaration_id::DeclarationId)
+    /// look themselves up in when they are displayed.
+    ///
+    /// This is null unless it is set by [Session::enter], which keeps the
+    /// session borrowed for as long as the pointer is set.
+    static ACTIVE_SESSION: Cell<*const Session> = const { Cell::new(std::ptr::null()) };
+}
+
+/// A compilation session, which owns the type engine and the declaration engine.
+///
+/// Every pass is given the session explicitly. The ids handed out by its engines
+/// only have a meaning within the session, so sessions can not share them, but
+/// separate sessions can be used concurrently.
+///
+/// The only exceptions are the `Display` and `Debug` impls of the types that
+/// refer to ids, which can not take the session as a parameter, so they look
+/// ids up in the session that is active on the current thread, see
+/// [Session::enter]. Comparing and hashing ids never looks them up.
+pub struct Session {
+    pub(crate) type_engine: TypeEngine,
+    pub(crate) declaration_engine: DeclarationEngine,
+    /// How many instances of the same generic declaration may be nested within
+    /// each other, see [Session::with_instantiation_depth_limit].
+    pub(crate) instantiation_depth_limit: usize,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session {
+            type_engine: TypeEngine::default(),
+            declaration_engine: DeclarationEngine::default(),
+            instantiation_depth_limit: DEFAULT_INSTANTIATION_DEPTH_LIMIT,
+        }
+    }
+}
+
+/// The default of [Session::with_instantiation_depth_limit].
+const DEFAULT_INSTANTIATION_DEPTH_LIMIT: usize = 64;
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Creates a session in which at most `limit` instances of the same generic
+    /// declaration may be nested within each other.
+    ///
+    /// A generic function that calls itself with a growing type argument, such
+    /// as `f::<Wrapper<T>>` inside of `f<T>`, needs a new instance at every
+    /// level, so compiling it fails once the limit is reached.
+    pub fn with_instantiation_depth_limit(limit: usize) -> Session {
+        Session {
+            instantiation_depth_limit: limit,
+            ..Session::default()
+        }
+    }
+
+    /// Removes every type and declaration from the session, so that it can be
+    /// reused for another compilation.
+    ///
+    /// Any id created before clearing the session is stale afterwards, and
+    /// looking it up is an error.
+    pub fn clear(&self) {
+        self.type_engine.clear();
+        self.declaration_engine.clear();
+    }
+
+    /// Makes this session the active session on the current thread for the
+    /// duration of `f`, so that ids display the types and declarations that
+    /// they refer to. Outside of any session, ids display their raw index.
+    ///
+    /// [compile](crate::compile) enters the session that it is given, so this
+    /// is only needed to display ids outside of compiling, such as when
+    /// debugging.
+    pub fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Restores the previously active session, even when `f` panics.
+        struct Restore(*const Session);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                ACTIVE_SESSION.with(|active| active.set(self.0));
+            }
+        }
+
+        let _restore = Restore(ACTIVE_SESSION.with(|active| active.replace(self)));
+        f()
+    }
+}
+
+/// Calls `f` with the session that is active on the current thread. This is
+/// only meant for displaying ids, everything else is given the session
+/// explicitly.
+///
+/// Returns `None`, without calling `f`, if there is no active session.
+pub(crate) fn with_active_session<R>(f: impl FnOnce(&Session) -> R) -> Option<R> {
+    let session = ACTIVE_SESSION.with(|active| active.get());
+    if session.is_null() {
+        return None;
+    }
+    // SAFETY: a non-null pointer was set by `Session::enter` on this thread,
+    // which holds a shared borrow of the session until it restores the previous
+    // pointer, even when unwinding. So the session is alive and not mutably
+    // borrowed for as long as the pointer is set. The reference can not outlive
+    // this call, as `R` can not borrow from it.
+    Some(f(unsafe { &*session }))
+}
@@ -27,9 +27,3 @@ impl fmt::Display for IntegerBits {
         }
     }
 }
-
-#[derive(Debug)]
-enum OccursCheck {
-    Occurs,
-    //DoesNotOccur
-}
@@ -1,6 +1,7 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+use crate::session::Session;
 use crate::types::copy_types::CopyTypes;
 
 use super::{type_id::TypeId, type_mapping::TypeMapping};
@@ -17,8 +18,8 @@ impl fmt::Display for TypeArgument {
 }
 
 impl CopyTypes for TypeArgument {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
-        self.type_id.copy_types(type_mapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
+        self.type_id.copy_types(session, type_mapping);
     }
 }
 
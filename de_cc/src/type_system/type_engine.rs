@@ -1,13 +1,22 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     collection_context::{
         collection_context::CollectionContext, collection_index::CollectionIndex,
     },
-    concurrent_slab::ConcurrentSlab,
-    declaration_engine::{declaration_engine::*, declaration_wrapper::DeclarationWrapper},
+    concurrent_slab::{ConcurrentSlab, SlabIndex},
+    declaration_engine::{
+        declaration_engine::*, declaration_id::DeclarationId,
+        declaration_wrapper::DeclarationWrapper,
+    },
     language::resolved::resolved_declaration::ResolvedStructField,
+    session::Session,
     types::{copy_types::CopyTypes, create_type_id::CreateTypeId},
+    union_find::UnionFind,
 };
 
 use super::{
@@ -15,20 +24,117 @@ use super::{
     type_argument::TypeArgument,
     type_id::TypeId,
     type_info::TypeInfo,
-    type_mapping::{insert_type_parameters, TypeMapping},
+    type_mapping::TypeMapping,
     type_parameter::TypeParameter,
+    IntegerBits,
 };
 
-use either::Either;
-use lazy_static::lazy_static;
+/// Used inside of type inference to store types, owned by a [Session].
+///
+/// Types that have been unified with each other are kept in the same set of a
+/// [UnionFind], and the type of the entire set is stored at the index of its
+/// representative.
+///
+/// Concrete types, which do not contain any type variables, are interned, so
+/// that every concrete type is only inserted once and identical concrete types
+/// share a [TypeId].
+///
+/// Unification can be done speculatively between [TypeEngine::snapshot] and
+/// [TypeEngine::rollback_to], which undoes every replaced type and every
+/// merged set since the snapshot.
+///
+/// The locks are always taken in the order `interned`, `sets`, `undo_log`, and
+/// the slab does not call back into the engine while it holds its own lock.
+#[derive(Default)]
+pub(crate) struct TypeEngine {
+    slab: ConcurrentSlab<TypeInfo>,
+    sets: Mutex<UnionFind>,
+    undo_log: Mutex<UndoLog>,
+    interned: Mutex<HashMap<InternKey, TypeId>>,
+}
+
+/// The key that a concrete type is interned by.
+///
+/// The types inside of an interned type are interned first, so they are
+/// compared by their ids, and the key does not depend on the contents of the
+/// engine or on the active session.
+#[derive(PartialEq, Eq, Hash)]
+enum InternKey {
+    Unit,
+    UnsignedInteger(IntegerBits),
+    Struct {
+        decl_id: DeclarationId,
+        type_arguments: Vec<TypeId>,
+        fields: Vec<(String, TypeId)>,
+    },
+}
 
-lazy_static! {
-    static ref TYPE_ENGINE: TypeEngine = TypeEngine::default();
+impl InternKey {
+    /// The key of the concrete type `ty`, whose inner types are interned.
+    fn new(ty: &TypeInfo) -> InternKey {
+        match ty {
+            TypeInfo::Unit => InternKey::Unit,
+            TypeInfo::UnsignedInteger(bits) => InternKey::UnsignedInteger(*bits),
+            TypeInfo::Struct {
+                decl_id,
+                type_parameters,
+                fields,
+                ..
+            } => InternKey::Struct {
+                decl_id: decl_id.untyped(),
+                type_arguments: type_parameters
+                    .iter()
+                    .map(|type_parameter| type_parameter.type_id)
+                    .collect(),
+                fields: fields
+                    .iter()
+                    .map(|field| (field.name.clone(), field.type_id))
+                    .collect(),
+            },
+            TypeInfo::ErrorRecovery
+            | TypeInfo::Unknown
+            | TypeInfo::UnknownGeneric { .. }
+            | TypeInfo::Custom { .. } => unreachable!("only concrete types are interned"),
+        }
+    }
 }
 
+/// The previous type at every index replaced since the oldest open snapshot.
 #[derive(Default)]
-struct TypeEngine {
-    slab: ConcurrentSlab<TypeInfo>,
+struct UndoLog {
+    replaced: Vec<(SlabIndex, Arc<TypeInfo>)>,
+    open_snapshots: usize,
+}
+
+/// A point in the history of a [TypeEngine] that it can be rolled back to.
+///
+/// Snapshots nest, and must be closed, with [TypeEngine::commit] or
+/// [TypeEngine::rollback_to], in the reverse order that they were taken.
+#[must_use]
+pub(crate) struct Snapshot {
+    sets: usize,
+    replaced: usize,
+    /// The number of snapshots that were open once this one was taken.
+    depth: usize,
+}
+
+impl UndoLog {
+    /// Checks that `snapshot` is the innermost open snapshot.
+    fn assert_innermost(&self, snapshot: &Snapshot) {
+        assert_eq!(
+            self.open_snapshots, snapshot.depth,
+            "snapshots must be closed in the reverse order that they were taken"
+        );
+        assert!(snapshot.replaced <= self.replaced.len());
+    }
+
+    fn close_snapshot(&mut self) {
+        self.open_snapshots -= 1;
+        // nothing can be rolled back once the outermost snapshot is closed
+        if self.open_snapshots == 0 {
+            self.replaced.clear();
+        }
+    }
 }
 
 impl TypeEngine {
@@ -37,101 +143,337 @@ impl TypeEngine {
         self.slab.debug_print();
     }
 
-    fn insert_type(&self, ty: TypeInfo) -> TypeId {
-        TypeId::new(self.slab.insert(ty))
+    /// Removes every type, which makes every [TypeId] created so far stale.
+    pub(crate) fn clear(&self) {
+        let mut interned = self.interned.lock().unwrap();
+        let mut sets = self.sets.lock().unwrap();
+        self.slab.clear();
+        sets.clear();
+        *self.undo_log.lock().unwrap() = UndoLog::default();
+        interned.clear();
     }
 
-    fn look_up_type_id(&self, id: TypeId) -> TypeInfo {
-        if id.occurs_check() {
-            panic!("recursive type has infinite size");
+    /// Starts recording every change to the engine, until the returned
+    /// snapshot is rolled back or committed. Snapshots nest, and must be closed
+    /// in the reverse order that they were taken in.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let sets = self.sets.lock().unwrap().snapshot();
+        let mut undo_log = self.undo_log.lock().unwrap();
+        undo_log.open_snapshots += 1;
+        Snapshot {
+            sets,
+            replaced: undo_log.replaced.len(),
+            depth: undo_log.open_snapshots,
         }
-        match self.slab.get(*id) {
-            TypeInfo::Ref(other) => self.look_up_type_id(other),
-            ty => ty,
+    }
+
+    /// Undoes every change to the engine since `snapshot` was taken.
+    ///
+    /// Types inserted since then are kept, but are no longer unified with any
+    /// type from before the snapshot.
+    pub(crate) fn rollback_to(&self, snapshot: Snapshot) {
+        let replaced = {
+            let mut undo_log = self.undo_log.lock().unwrap();
+            undo_log.assert_innermost(&snapshot);
+            let replaced = undo_log.replaced.split_off(snapshot.replaced);
+            undo_log.close_snapshot();
+            replaced
+        };
+        for (index, prev_info) in replaced.into_iter().rev() {
+            // clearing the engine empties the undo log, so the indices in it are
+            // never stale
+            self.slab.set(index, prev_info).unwrap();
         }
+        self.sets.lock().unwrap().rollback_to(snapshot.sets);
     }
 
-    fn look_up_type_id_raw(&self, id: TypeId) -> TypeInfo {
-        self.slab.get(*id)
+    /// Keeps every change to the engine since `snapshot` was taken.
+    ///
+    /// The changes can still be undone by rolling back to a snapshot that was
+    /// taken before `snapshot`.
+    pub(crate) fn commit(&self, snapshot: Snapshot) {
+        let mut sets = self.sets.lock().unwrap();
+        let mut undo_log = self.undo_log.lock().unwrap();
+        undo_log.assert_innermost(&snapshot);
+        undo_log.close_snapshot();
+        sets.commit(snapshot.sets);
     }
 
-    fn unify_types(&self, received: TypeId, expected: TypeId) -> Result<(), String> {
-        if self.occurs_check(received, expected) {
-            panic!("recursive types have infinite size");
+    /// Replaces the type at `index` if it still is `prev_info`, recording the
+    /// change if there is an open snapshot. Returns the actual type otherwise.
+    fn replace(
+        &self,
+        index: SlabIndex,
+        prev_info: Arc<TypeInfo>,
+        info: Arc<TypeInfo>,
+    ) -> Result<Option<Arc<TypeInfo>>, String> {
+        let actual_info = self.slab.replace(index, &prev_info, info)?;
+        if actual_info.is_none() {
+            let mut undo_log = self.undo_log.lock().unwrap();
+            if undo_log.open_snapshots > 0 {
+                undo_log.replaced.push((index, prev_info));
+            }
         }
-        match (self.slab.get(*received), self.slab.get(*expected)) {
-            // if the two types are the same literal then we are done
-            (TypeInfo::Unit, TypeInfo::Unit) => Ok(()),
-            (TypeInfo::UnsignedInteger(a), TypeInfo::UnsignedInteger(b)) if a == b => Ok(()),
+        Ok(actual_info)
+    }
 
-            // if either of the types are unknown
-            (TypeInfo::Unknown, _) => {
-                match self
-                    .slab
-                    .replace(*received, &TypeInfo::Unknown, TypeInfo::Ref(expected))
+    /// Inserts `ty`, or returns the id of `ty` if it is a concrete type that
+    /// has already been inserted.
+    ///
+    /// A type that refers to a stale id is inserted as it is, so that the
+    /// error is returned once that id is looked up.
+    fn insert_type(&self, ty: TypeInfo) -> TypeId {
+        match self.intern(&ty) {
+            Ok(Some(type_id)) => type_id,
+            Ok(None) | Err(_) => self.insert_fresh(ty),
+        }
+    }
+
+    /// Interns `ty` if it is concrete.
+    fn intern(&self, ty: &TypeInfo) -> Result<Option<TypeId>, String> {
+        if !self.is_concrete(ty)? {
+            return Ok(None);
+        }
+        self.intern_memo(ty.clone(), &mut HashMap::new()).map(Some)
+    }
+
+    /// Interns the concrete type `ty`, using memoization of the representatives
+    /// of the types inside of it that have already been interned.
+    fn intern_memo(
+        &self,
+        ty: TypeInfo,
+        memo: &mut HashMap<usize, TypeId>,
+    ) -> Result<TypeId, String> {
+        // point the types inside of `ty` at their interned ids, so that the
+        // interned types never depend on a type variable
+        let ty = match ty {
+            TypeInfo::Struct {
+                decl_id,
+                name,
+                mut type_parameters,
+                mut fields,
+            } => {
+                for type_id in type_parameters
+                    .iter_mut()
+                    .map(|type_parameter| &mut type_parameter.type_id)
+                    .chain(fields.iter_mut().map(|field| &mut field.type_id))
                 {
-                    None => Ok(()),
-                    Some(_) => self.unify_types(received, expected),
+                    let root = self.find(*type_id)?;
+                    *type_id = match memo.get(&*root) {
+                        Some(interned_id) => *interned_id,
+                        None => {
+                            let interned_id =
+                                self.intern_memo(TypeInfo::clone(&*self.slab.get(root)?), memo)?;
+                            memo.insert(*root, interned_id);
+                            interned_id
+                        }
+                    };
+                }
+                TypeInfo::Struct {
+                    decl_id,
+                    name,
+                    type_parameters,
+                    fields,
                 }
             }
-            (_, TypeInfo::Unknown) => {
-                match self
-                    .slab
-                    .replace(*expected, &TypeInfo::Unknown, TypeInfo::Ref(received))
+            ty => ty,
+        };
+        let key = InternKey::new(&ty);
+        let mut interned = self.interned.lock().unwrap();
+        if let Some(type_id) = interned.get(&key) {
+            return Ok(*type_id);
+        }
+        let type_id = self.insert_fresh(ty);
+        interned.insert(key, type_id);
+        Ok(type_id)
+    }
+
+    /// The interned id of the type that `type_id` refers to, if it is concrete.
+    ///
+    /// Identical concrete types have the same interned id, no matter which
+    /// type variables they have been unified with, so it can be used as a
+    /// canonical key for the type.
+    fn canonical_id(&self, type_id: TypeId) -> Result<Option<TypeId>, String> {
+        self.intern(&*self.look_up_type_id(type_id)?)
+    }
+
+    fn insert_fresh(&self, ty: TypeInfo) -> TypeId {
+        // the set and the slab entry of a type must have the same index
+        let mut sets = self.sets.lock().unwrap();
+        let index = self.slab.insert(ty);
+        sets.push();
+        TypeId::new(index)
+    }
+
+    /// Whether `ty` is fully known, and does not contain any type variables.
+    fn is_concrete(&self, ty: &TypeInfo) -> Result<bool, String> {
+        self.is_concrete_memo(ty, &mut HashMap::new())
+    }
+
+    /// Checks whether `ty` is concrete using memoization of the representatives
+    /// that have already been checked.
+    fn is_concrete_memo(
+        &self,
+        ty: &TypeInfo,
+        memo: &mut HashMap<usize, bool>,
+    ) -> Result<bool, String> {
+        match ty {
+            TypeInfo::Unit | TypeInfo::UnsignedInteger(_) => Ok(true),
+            TypeInfo::Struct {
+                type_parameters,
+                fields,
+                ..
+            } => {
+                for type_id in type_parameters
+                    .iter()
+                    .map(|type_parameter| type_parameter.type_id)
+                    .chain(fields.iter().map(|field| field.type_id))
                 {
-                    None => Ok(()),
-                    Some(_) => self.unify_types(received, expected),
+                    let root = self.find(type_id)?;
+                    let is_concrete = match memo.get(&*root) {
+                        Some(is_concrete) => *is_concrete,
+                        None => {
+                            let is_concrete =
+                                self.is_concrete_memo(&*self.slab.get(root)?, memo)?;
+                            memo.insert(*root, is_concrete);
+                            is_concrete
+                        }
+                    };
+                    if !is_concrete {
+                        return Ok(false);
+                    }
                 }
+                Ok(true)
             }
+            TypeInfo::ErrorRecovery
+            | TypeInfo::Unknown
+            | TypeInfo::UnknownGeneric { .. }
+            | TypeInfo::Custom { .. } => Ok(false),
+        }
+    }
+
+    /// The index of the representative of the set of types that `id` has been
+    /// unified with. Returns an error if `id` is stale, or belongs to another
+    /// engine.
+    pub(super) fn find(&self, id: TypeId) -> Result<SlabIndex, String> {
+        let index = id.slab_index();
+        self.slab.validate(index)?;
+        let root = self.sets.lock().unwrap().find(*index);
+        Ok(index.with_index(root))
+    }
 
-            // follow any references
-            (TypeInfo::Ref(received), TypeInfo::Ref(expected)) if received == expected => Ok(()),
-            (TypeInfo::Ref(received), _) => self.unify_types(received, expected),
-            (_, TypeInfo::Ref(expected)) => self.unify_types(received, expected),
+    pub(super) fn look_up_type_id(&self, id: TypeId) -> Result<Arc<TypeInfo>, String> {
+        self.slab.get(self.find(id)?)
+    }
 
+    fn are_equivalent(&self, a: TypeId, b: TypeId) -> Result<bool, String> {
+        // types in the same set, including identical interned types, are
+        // equivalent without having to compare their structure
+        Ok(self.find(a)? == self.find(b)?
+            || self.are_equivalent_infos(&*self.look_up_type_id(a)?, &*self.look_up_type_id(b)?)?)
+    }
+
+    /// Whether `a` and `b` are structurally the same type, comparing the types
+    /// inside of them with [TypeEngine::are_equivalent].
+    fn are_equivalent_infos(&self, a: &TypeInfo, b: &TypeInfo) -> Result<bool, String> {
+        match (a, b) {
             (
-                TypeInfo::UnknownGeneric { name: l_name },
-                TypeInfo::UnknownGeneric { name: r_name },
-            ) if l_name.as_str() == r_name.as_str() => Ok(()),
-            (ref received_info @ TypeInfo::UnknownGeneric { .. }, _) => {
-                self.slab
-                    .replace(*received, received_info, TypeInfo::Ref(expected));
-                Ok(())
+                TypeInfo::Struct {
+                    decl_id: a_decl_id,
+                    type_parameters: a_parameters,
+                    ..
+                },
+                TypeInfo::Struct {
+                    decl_id: b_decl_id,
+                    type_parameters: b_parameters,
+                    ..
+                },
+            ) => {
+                if a_decl_id != b_decl_id || a_parameters.len() != b_parameters.len() {
+                    return Ok(false);
+                }
+                for (a_param, b_param) in a_parameters.iter().zip(b_parameters.iter()) {
+                    if !self.are_equivalent(a_param.type_id, b_param.type_id)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            // type parameters with the same name can be declared by different
+            // declarations, so they are only equivalent if they have the same root
+            (TypeInfo::UnknownGeneric { .. }, TypeInfo::UnknownGeneric { .. }) => Ok(false),
+            (a, b) => Ok(a == b),
+        }
+    }
+
+    /// Merges the sets of `received` and `expected`, which become the type
+    /// `info`.
+    ///
+    /// The type of the merged set is replaced before the sets are merged, so
+    /// that the sets are left apart if another thread replaced the type first,
+    /// and unification is retried from the start.
+    fn union(&self, received: TypeId, expected: TypeId, info: Arc<TypeInfo>) -> Result<(), String> {
+        let (received_root, expected_root) = (self.find(received)?, self.find(expected)?);
+        for root in [received_root, expected_root] {
+            if let Some(chain) = self.occurs_check(root, &info)? {
+                return Err(occurs_check_error(&chain, &*self.slab.get(root)?));
             }
-            (_, ref expected_info @ TypeInfo::UnknownGeneric { .. }) => {
-                self.slab
-                    .replace(*expected, expected_info, TypeInfo::Ref(received));
+        }
+        let mut sets = self.sets.lock().unwrap();
+        let root = received_root.with_index(sets.union_root(*received_root, *expected_root));
+        let prev_info = self.slab.get(root)?;
+        match self.replace(root, prev_info, info)? {
+            None => {
+                sets.union(*received_root, *expected_root);
                 Ok(())
             }
+            Some(_) => {
+                drop(sets);
+                self.unify_types(received, expected)
+            }
+        }
+    }
+
+    fn unify_types(&self, received: TypeId, expected: TypeId) -> Result<(), String> {
+        // type parameters are rigid inside of their own declaration, so they
+        // only unify with themselves, which is when they share a root here, and
+        // are substituted when monomorphizing
+        let (received_root, expected_root) = (self.find(received)?, self.find(expected)?);
+        if received_root == expected_root {
+            return Ok(());
+        }
+        let (received_info, expected_info) =
+            (self.slab.get(received_root)?, self.slab.get(expected_root)?);
+        match (&*received_info, &*expected_info) {
+            // if the two types are the same literal then we are done
+            (TypeInfo::Unit, TypeInfo::Unit) => Ok(()),
+            (TypeInfo::UnsignedInteger(a), TypeInfo::UnsignedInteger(b)) if a == b => Ok(()),
+
+            // if either of the types are unknown
+            (TypeInfo::Unknown, _) => self.union(received, expected, expected_info),
+            (_, TypeInfo::Unknown) => self.union(received, expected, received_info),
 
+            // structs are the same type if they come from the same declaration
+            // and have the same type arguments
             (
                 TypeInfo::Struct {
-                    name: a_name,
-                    fields: a_fields,
+                    decl_id: a_decl_id,
                     type_parameters: a_parameters,
+                    ..
                 },
                 TypeInfo::Struct {
-                    name: b_name,
-                    fields: b_fields,
+                    decl_id: b_decl_id,
                     type_parameters: b_parameters,
+                    ..
                 },
-            ) => {
-                if a_name != b_name
-                    || a_fields.len() != b_fields.len()
-                    || a_parameters.len() != b_parameters.len()
-                {
-                    return Err("type mismatch".to_string());
-                }
-                for (a_field, b_field) in a_fields.iter().zip(b_fields.iter()) {
-                    self.unify_types(a_field.type_id, b_field.type_id)?;
-                }
+            ) if *a_decl_id == *b_decl_id && a_parameters.len() == b_parameters.len() => {
                 for (a_param, b_param) in a_parameters.iter().zip(b_parameters.iter()) {
                     self.unify_types(a_param.type_id, b_param.type_id)?;
                 }
                 Ok(())
             }
 
-            (received_info, expected_info) => Err(format!(
+            _ => Err(format!(
                 "type mismatch, expected: {}, received: {}",
                 expected_info, received_info
             )),
@@ -139,20 +481,17 @@ impl TypeEngine {
     }
 
     fn resolve_type(&self, type_id: TypeId) -> Result<ResolvedType, String> {
-        if type_id.occurs_check() {
-            panic!("recursive type has infinite size");
-        }
-        match self.slab.get(*type_id) {
-            TypeInfo::UnsignedInteger(bits) => Ok(ResolvedType::UnsignedInteger(bits)),
-            TypeInfo::Ref(id) => self.resolve_type(id),
+        match &*self.look_up_type_id(type_id)? {
+            TypeInfo::UnsignedInteger(bits) => Ok(ResolvedType::UnsignedInteger(*bits)),
             TypeInfo::Unit => Ok(ResolvedType::Unit),
             TypeInfo::Struct {
                 name,
                 type_parameters,
                 fields,
+                ..
             } => {
                 let type_parameters = type_parameters
-                    .into_iter()
+                    .iter()
                     .map(|type_parameter| {
                         Ok(ResolvedTypeParameter {
                             type_info: self.resolve_type(type_parameter.type_id)?,
@@ -160,16 +499,16 @@ impl TypeEngine {
                     })
                     .collect::<Result<_, String>>()?;
                 let fields = fields
-                    .into_iter()
+                    .iter()
                     .map(|field| {
                         Ok(ResolvedStructField {
-                            name: field.name,
+                            name: field.name.clone(),
                             type_info: self.resolve_type(field.type_id)?,
                         })
                     })
                     .collect::<Result<_, String>>()?;
                 Ok(ResolvedType::Struct {
-                    name,
+                    name: name.clone(),
                     type_parameters,
                     fields,
                 })
@@ -183,103 +522,43 @@ impl TypeEngine {
         }
     }
 
-    fn resolve_custom_types(
+    fn type_matches_type_parameter(
         &self,
         type_id: TypeId,
-        cc: &CollectionContext,
-        current_index: CollectionIndex,
-    ) -> Result<(), String> {
-        if type_id.occurs_check() {
-            panic!("recursive type has infinite size");
-        }
-        match self.slab.get(*type_id) {
-            TypeInfo::Ref(inner_id) => self.resolve_custom_types(inner_id, cc, current_index),
-            TypeInfo::Custom {
-                name,
-                mut type_arguments,
-            } => {
-                let decl_id = cc.get_symbol(current_index, &name)?;
-                match de_look_up_decl_id(*decl_id.inner_ref()) {
-                    DeclarationWrapper::Struct(mut struct_decl) => {
-                        // save the previous type info
-                        let prev_info = TypeInfo::Custom {
-                            name,
-                            type_arguments: type_arguments.clone(),
-                        };
-
-                        // do type inference on the type arguments
-                        for type_argument in type_arguments.iter_mut() {
-                            resolve_custom_types(type_argument.type_id, cc, current_index).unwrap();
-                        }
-
-                        // monomorphize the struct declaration into a new copy
-                        self.monomorphize(&mut struct_decl, &mut type_arguments)?;
-
-                        // add the new copy to the declaration engine
-                        de_add_monomorphized_struct_copy(*decl_id.inner_ref(), struct_decl.clone());
-
-                        // get the new type info
-                        let new_info = self.look_up_type_id(struct_decl.create_type_id());
-
-                        // replace the id with the new type info
-                        self.slab.replace(*type_id, &prev_info, new_info);
-
-                        Ok(())
-                    }
-                    got => Err(format!("err, found: {}", got)),
-                }
-            }
-            _ => Ok(()),
-        }
+        mapping: &TypeMapping,
+    ) -> Option<TypeId> {
+        self.type_matches_type_parameter_memo(type_id, mapping, &mut HashMap::new())
     }
 
-    fn monomorphize<T>(
+    /// Performs [TypeEngine::type_matches_type_parameter] using memoization of
+    /// the representatives that have already been substituted.
+    fn type_matches_type_parameter_memo(
         &self,
-        value: &mut T,
-        type_arguments: &mut [TypeArgument],
-    ) -> Result<(), String>
-    where
-        T: MonomorphizeHelper + CopyTypes,
-    {
-        match (
-            value.type_parameters().is_empty(),
-            type_arguments.is_empty(),
-        ) {
-            (true, true) => Ok(()),
-            (false, true) => {
-                let type_mapping = insert_type_parameters(value.type_parameters().to_vec());
-                value.copy_types(&type_mapping);
-                Ok(())
-            }
-            (true, false) => Err("does not take type arguments".to_string()),
-            (false, false) => {
-                if value.type_parameters().len() != type_arguments.len() {
-                    return Err("incorrect number of type arguments".to_string());
-                }
-                let type_mapping = insert_type_parameters(value.type_parameters().to_vec());
-                for ((_, interim_type), type_arg) in type_mapping.iter().zip(type_arguments.iter())
-                {
-                    self.unify_types(*interim_type, type_arg.type_id)?;
-                }
-                value.copy_types(&type_mapping);
-                Ok(())
-            }
+        type_id: TypeId,
+        mapping: &TypeMapping,
+        memo: &mut HashMap<usize, Option<TypeId>>,
+    ) -> Option<TypeId> {
+        let root = self.find(type_id).ok()?;
+        if let Some(matching_id) = memo.get(&*root) {
+            return *matching_id;
         }
+        let matching_id = self.substitute_type_parameters(type_id, mapping, memo);
+        memo.insert(*root, matching_id);
+        matching_id
     }
 
-    fn type_matches_type_parameter(
+    fn substitute_type_parameters(
         &self,
         type_id: TypeId,
         mapping: &TypeMapping,
+        memo: &mut HashMap<usize, Option<TypeId>>,
     ) -> Option<TypeId> {
-        if type_id.occurs_check() {
-            panic!("recursive type has infinite size");
-        }
-        let type_info = self.look_up_type_id(type_id);
-        match type_info {
+        let root = self.find(type_id).ok()?;
+        let type_info = self.slab.get(root).ok()?;
+        match &*type_info {
             TypeInfo::UnknownGeneric { .. } => {
                 for (param, ty_id) in mapping.iter() {
-                    if self.look_up_type_id(*param) == type_info {
+                    if self.find(*param).ok() == Some(root) {
                         return Some(*ty_id);
                     }
                 }
@@ -287,120 +566,523 @@ impl TypeEngine {
             }
             TypeInfo::Custom { .. } => {
                 for (param, ty_id) in mapping.iter() {
-                    if self.look_up_type_id(*param) == type_info {
+                    if self.look_up_type_id(*param).ok().as_ref() == Some(&type_info) {
                         return Some(*ty_id);
                     }
                 }
                 None
             }
             TypeInfo::Struct {
+                decl_id,
                 fields,
                 name,
                 type_parameters,
             } => {
-                let mut new_type_parameters = type_parameters;
+                let mut changed = false;
+                let mut new_type_parameters = type_parameters.clone();
                 for new_param in new_type_parameters.iter_mut() {
                     if let Some(matching_id) =
-                        self.type_matches_type_parameter(new_param.type_id, mapping)
+                        self.type_matches_type_parameter_memo(new_param.type_id, mapping, memo)
                     {
-                        new_param.type_id = self.insert_type(TypeInfo::Ref(matching_id));
+                        new_param.type_id = matching_id;
+                        changed = true;
                     }
                 }
-                let mut new_fields = fields;
+                let mut new_fields = fields.clone();
                 for new_field in new_fields.iter_mut() {
                     if let Some(matching_id) =
-                        self.type_matches_type_parameter(new_field.type_id, mapping)
+                        self.type_matches_type_parameter_memo(new_field.type_id, mapping, memo)
                     {
-                        new_field.type_id = self.insert_type(TypeInfo::Ref(matching_id));
+                        new_field.type_id = matching_id;
+                        changed = true;
                     }
                 }
+                if !changed {
+                    return None;
+                }
                 Some(self.insert_type(TypeInfo::Struct {
+                    decl_id: *decl_id,
                     fields: new_fields,
-                    name,
+                    name: name.clone(),
                     type_parameters: new_type_parameters,
                 }))
             }
             TypeInfo::ErrorRecovery
             | TypeInfo::Unknown
             | TypeInfo::Unit
-            | TypeInfo::Ref(_)
             | TypeInfo::UnsignedInteger(_) => None,
         }
     }
 
-    /// Returns true if the same type occurs in both gives types---i.e. if unifying these types would create an infinite cycle.
+    /// Returns the chain of types through which the type with representative
+    /// `root` occurs within `info`, if it does---i.e. if binding `root` to
+    /// `info` would create an infinite cycle.
     ///
     /// "occurs check: a check for whether the same variable occurs on both sides and, if it does, decline to unify"
     /// https://papl.cs.brown.edu/2016/Type_Inference.html
-    fn occurs_check(&self, left: TypeId, right: TypeId) -> bool {
-        let left_ids = left.occurs_check_memo(HashSet::new());
-        let right_ids = right.occurs_check_memo(HashSet::new());
-        match (left_ids, right_ids) {
-            (Either::Left(left_ids), Either::Left(right_ids)) => {
-                for l in left_ids.iter() {
-                    if right_ids.contains(l) {
-                        return true;
-                    }
-                }
-                false
+    fn occurs_check(
+        &self,
+        root: SlabIndex,
+        info: &TypeInfo,
+    ) -> Result<Option<Vec<TypeInfo>>, String> {
+        self.occurs_check_memo(root, info, &mut HashSet::new())
+    }
+
+    /// Performs the 'occurs check' using memoization of the representatives
+    /// that have already been searched.
+    fn occurs_check_memo(
+        &self,
+        root: SlabIndex,
+        info: &TypeInfo,
+        memo: &mut HashSet<usize>,
+    ) -> Result<Option<Vec<TypeInfo>>, String> {
+        let inner_ids = match info {
+            TypeInfo::Struct {
+                type_parameters,
+                fields,
+                ..
+            } => type_parameters
+                .iter()
+                .map(|type_parameter| type_parameter.type_id)
+                .chain(fields.iter().map(|field| field.type_id))
+                .collect(),
+            // the type arguments of a custom type are not inserted until it
+            // is resolved
+            TypeInfo::ErrorRecovery
+            | TypeInfo::Unknown
+            | TypeInfo::UnknownGeneric { .. }
+            | TypeInfo::Custom { .. }
+            | TypeInfo::Unit
+            | TypeInfo::UnsignedInteger(_) => vec![],
+        };
+        for inner_id in inner_ids {
+            let inner_root = self.find(inner_id)?;
+            if inner_root == root {
+                return Ok(Some(vec![info.clone()]));
+            }
+            if !memo.insert(*inner_root) {
+                continue;
+            }
+            if let Some(mut chain) =
+                self.occurs_check_memo(root, &*self.slab.get(inner_root)?, memo)?
+            {
+                chain.insert(0, info.clone());
+                return Ok(Some(chain));
             }
-            _ => true,
         }
+        Ok(None)
     }
 }
 
 #[allow(dead_code)]
-pub(crate) fn te_debug_print() {
-    TYPE_ENGINE.debug_print()
+pub(crate) fn te_debug_print(session: &Session) {
+    session.type_engine.debug_print()
+}
+
+pub(crate) fn insert_type(session: &Session, ty: TypeInfo) -> TypeId {
+    session.type_engine.insert_type(ty)
+}
+
+/// Inserts `ty`, as it is written inside of a declaration with the type
+/// parameters `type_parameters`. A type parameter that `ty` names is not
+/// inserted, but refers to the type parameter that declares it.
+pub(crate) fn insert_type_in_scope(
+    session: &Session,
+    ty: TypeInfo,
+    type_parameters: &[TypeParameter],
+) -> TypeId {
+    if let TypeInfo::UnknownGeneric { name } = &ty {
+        if let Some(type_parameter) = type_parameters
+            .iter()
+            .find(|type_parameter| &type_parameter.name == name)
+        {
+            return type_parameter.type_id;
+        }
+    }
+    insert_type(session, ty)
 }
 
-pub(crate) fn insert_type(ty: TypeInfo) -> TypeId {
-    TYPE_ENGINE.insert_type(ty)
+pub(crate) fn look_up_type_id(session: &Session, id: TypeId) -> Result<Arc<TypeInfo>, String> {
+    session.type_engine.look_up_type_id(id)
 }
 
-pub(crate) fn look_up_type_id(id: TypeId) -> TypeInfo {
-    TYPE_ENGINE.look_up_type_id(id)
+/// Whether `a` and `b` refer to the same type, either because they have been
+/// unified or because their types are structurally equal. Unlike comparing the
+/// ids themselves, this looks up the types in the type engine.
+pub(crate) fn are_equivalent_types(
+    session: &Session,
+    a: TypeId,
+    b: TypeId,
+) -> Result<bool, String> {
+    session.type_engine.are_equivalent(a, b)
 }
 
-pub(crate) fn look_up_type_id_raw(id: TypeId) -> TypeInfo {
-    TYPE_ENGINE.look_up_type_id_raw(id)
+/// The interned id of the type that `type_id` refers to, if it is concrete,
+/// see [TypeEngine::canonical_id].
+pub(crate) fn canonical_type_id(
+    session: &Session,
+    type_id: TypeId,
+) -> Result<Option<TypeId>, String> {
+    session.type_engine.canonical_id(type_id)
 }
 
-pub(crate) fn unify_types(received: TypeId, expected: TypeId) -> Result<(), String> {
-    TYPE_ENGINE.unify_types(received, expected)
+/// The interned ids of the types of `type_parameters`, if they are all
+/// concrete.
+pub(crate) fn concrete_type_arguments(
+    session: &Session,
+    type_parameters: &[TypeParameter],
+) -> Result<Option<Vec<TypeId>>, String> {
+    type_parameters
+        .iter()
+        .map(|type_parameter| canonical_type_id(session, type_parameter.type_id))
+        .collect::<Result<Option<Vec<_>>, _>>()
 }
 
-pub(crate) fn resolve_type(type_id: TypeId) -> Result<ResolvedType, String> {
-    TYPE_ENGINE.resolve_type(type_id)
+/// Unifies `received` with `expected`. If they do not unify, the types that
+/// were unified before the mismatch was found are left as they were.
+pub(crate) fn unify_types(
+    session: &Session,
+    received: TypeId,
+    expected: TypeId,
+) -> Result<(), String> {
+    let type_engine = &session.type_engine;
+    let snapshot = type_engine.snapshot();
+    let result = type_engine.unify_types(received, expected);
+    match result {
+        Ok(()) => type_engine.commit(snapshot),
+        Err(_) => type_engine.rollback_to(snapshot),
+    }
+    result
 }
 
+pub(crate) fn resolve_type(session: &Session, type_id: TypeId) -> Result<ResolvedType, String> {
+    session.type_engine.resolve_type(type_id)
+}
+
+/// Resolves the custom type that `type_id` refers to, if any, to a copy of the
+/// struct that it names. Type parameters named in its type arguments refer to
+/// `type_parameters`, the type parameters of the surrounding declaration.
 pub(crate) fn resolve_custom_types(
-    id: TypeId,
+    session: &Session,
+    type_id: TypeId,
     cc: &CollectionContext,
     current_index: CollectionIndex,
+    type_parameters: &[TypeParameter],
 ) -> Result<(), String> {
-    TYPE_ENGINE.resolve_custom_types(id, cc, current_index)
+    let type_engine = &session.type_engine;
+    let prev_info = type_engine.look_up_type_id(type_id)?;
+    match &*prev_info {
+        TypeInfo::Custom {
+            name,
+            type_arguments,
+        } => {
+            let decl_id = cc.get_symbol(current_index, name)?;
+            match &*de_look_up_decl_id(session, *decl_id.inner_ref())? {
+                DeclarationWrapper::Struct(struct_decl) => {
+                    let struct_id = de_expect_decl_id(session, *decl_id.inner_ref())?;
+
+                    // do type inference on the type arguments
+                    let type_arguments = type_arguments
+                        .iter()
+                        .map(|type_argument| TypeArgument {
+                            type_id: insert_type_in_scope(
+                                session,
+                                type_argument.clone(),
+                                type_parameters,
+                            ),
+                        })
+                        .collect::<Vec<_>>();
+                    for type_argument in type_arguments.iter() {
+                        resolve_custom_types(
+                            session,
+                            type_argument.type_id,
+                            cc,
+                            current_index,
+                            type_parameters,
+                        )?;
+                    }
+
+                    // monomorphize the struct declaration into a new copy
+                    let mut struct_decl = (**struct_decl).clone();
+                    monomorphize(session, &mut struct_decl, &type_arguments)?;
+
+                    // add the new copy to the declaration engine, or reuse the
+                    // copy for the same type arguments
+                    let copy_id = de_add_monomorphized_struct_copy(
+                        session,
+                        struct_id,
+                        current_index,
+                        struct_decl,
+                    )?;
+                    let struct_decl = de_get_struct(session, copy_id)?;
+
+                    // get the new type info
+                    let new_info = type_engine
+                        .look_up_type_id(struct_decl.create_type_id(session, struct_id))?;
+
+                    // replace the id with the new type info
+                    let root = type_engine.find(type_id)?;
+                    if let Some(chain) = type_engine.occurs_check(root, &new_info)? {
+                        return Err(occurs_check_error(&chain, &new_info));
+                    }
+                    type_engine.replace(root, prev_info.clone(), new_info)?;
+
+                    Ok(())
+                }
+                got => Err(format!("err, found: {}", got)),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Describes the failed occurs check of `info`, which occurs within itself
+/// through the types in `chain`.
+fn occurs_check_error(chain: &[TypeInfo], info: &TypeInfo) -> String {
+    format!(
+        "recursive type has infinite size: {}",
+        chain
+            .iter()
+            .chain(iter::once(info))
+            .map(|type_info| type_info.to_string())
+            .collect::<Vec<_>>()
+            .join(" contains ")
+    )
 }
 
 pub(crate) fn monomorphize<T>(
+    session: &Session,
     value: &mut T,
-    type_arguments: &mut [TypeArgument],
+    type_arguments: &[TypeArgument],
 ) -> Result<(), String>
 where
     T: MonomorphizeHelper + CopyTypes,
 {
-    TYPE_ENGINE.monomorphize(value, type_arguments)
+    match (
+        value.type_parameters().is_empty(),
+        type_arguments.is_empty(),
+    ) {
+        (true, true) => Ok(()),
+        (false, true) => {
+            // substitute every type parameter with a new type variable, to be
+            // inferred from the uses of the copy
+            let type_mapping = value
+                .type_parameters()
+                .iter()
+                .map(|type_param| (type_param.type_id, insert_type(session, TypeInfo::Unknown)))
+                .collect();
+            value.copy_types(session, &type_mapping);
+            Ok(())
+        }
+        (true, false) => Err("does not take type arguments".to_string()),
+        (false, false) => {
+            if value.type_parameters().len() != type_arguments.len() {
+                return Err("incorrect number of type arguments".to_string());
+            }
+            let type_mapping = value
+                .type_parameters()
+                .iter()
+                .zip(type_arguments.iter())
+                .map(|(type_param, type_arg)| (type_param.type_id, type_arg.type_id))
+                .collect();
+            value.copy_types(session, &type_mapping);
+            Ok(())
+        }
+    }
 }
 
 pub(crate) fn type_matches_type_parameter(
+    session: &Session,
     type_id: TypeId,
     mapping: &TypeMapping,
 ) -> Option<TypeId> {
-    TYPE_ENGINE.type_matches_type_parameter(type_id, mapping)
+    session
+        .type_engine
+        .type_matches_type_parameter(type_id, mapping)
 }
 
 pub(crate) trait MonomorphizeHelper {
+    #[allow(dead_code)]
     fn name(&self) -> &str;
     fn type_parameters(&self) -> &[TypeParameter];
+    #[allow(dead_code)]
     fn type_parameters_mut(&mut self) -> &mut [TypeParameter];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::ty::typed_declaration::TyStructDeclaration;
+
+    #[test]
+    fn interned_types_share_an_id_test() {
+        let type_engine = TypeEngine::default();
+        let a = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let b = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let c = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::ThirtyTwo));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // a type variable that is bound to a concrete type has the interned id
+        // of that type as its canonical id
+        let variable = type_engine.insert_type(TypeInfo::Unknown);
+        type_engine.unify_types(variable, a).unwrap();
+        assert_eq!(type_engine.canonical_id(variable), Ok(Some(a)));
+        assert_eq!(type_engine.canonical_id(b), Ok(Some(a)));
+
+        // none of the repeated types were inserted into the slab
+        let fresh = type_engine.insert_type(TypeInfo::Unknown);
+        assert_eq!(*fresh, *variable + 1);
+    }
+
+    #[test]
+    fn type_parameters_are_rigid_test() {
+        let type_engine = TypeEngine::default();
+        let t = |name: &str| TypeInfo::UnknownGeneric {
+            name: name.to_string(),
+        };
+
+        // two declarations that both have a type parameter named `T`
+        let f_t = type_engine.insert_type(t("T"));
+        let g_t = type_engine.insert_type(t("T"));
+        let err = type_engine.unify_types(f_t, g_t).unwrap_err();
+        assert_eq!(err, "type mismatch, expected: T, received: T");
+        assert_eq!(type_engine.are_equivalent(f_t, g_t), Ok(false));
+
+        // a type parameter unifies with itself, and with type variables
+        assert_eq!(type_engine.unify_types(f_t, f_t), Ok(()));
+        let variable = type_engine.insert_type(TypeInfo::Unknown);
+        assert_eq!(type_engine.unify_types(variable, f_t), Ok(()));
+        assert_eq!(type_engine.are_equivalent(variable, f_t), Ok(true));
+        assert!(type_engine.unify_types(variable, g_t).is_err());
+
+        // only the declaring type parameter is substituted
+        let u64_id = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let mapping = TypeMapping::from([(f_t, u64_id)]);
+        assert_eq!(
+            type_engine.type_matches_type_parameter(variable, &mapping),
+            Some(u64_id)
+        );
+        assert_eq!(type_engine.type_matches_type_parameter(g_t, &mapping), None);
+    }
+
+    #[test]
+    fn failed_unification_rolls_back_test() {
+        let session = Session::new();
+        let type_engine = &session.type_engine;
+        let u64_id = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let u32_id = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::ThirtyTwo));
+        let variable = type_engine.insert_type(TypeInfo::Unknown);
+
+        // `Data<x, x>` and `Data<u64, u32>` only fail to unify after `x` has
+        // been unified with `u64`
+        let decl_id = de_insert_struct(
+            &session,
+            TyStructDeclaration {
+                name: "Data".to_string(),
+                file: "bob.sw".to_string(),
+                path: vec![],
+                type_parameters: vec![],
+                fields: vec![],
+            },
+        );
+        let data = |type_arguments: [TypeId; 2]| TypeInfo::Struct {
+            decl_id,
+            name: "Data".to_string(),
+            type_parameters: type_arguments
+                .iter()
+                .map(|type_id| TypeParameter {
+                    name: "T".to_string(),
+                    type_id: *type_id,
+                    trait_constraint: None,
+                })
+                .collect(),
+            fields: vec![],
+        };
+        let received = type_engine.insert_type(data([variable, variable]));
+        let expected = type_engine.insert_type(data([u64_id, u32_id]));
+        assert!(unify_types(&session, received, expected).is_err());
+        assert_eq!(
+            *type_engine.look_up_type_id(variable).unwrap(),
+            TypeInfo::Unknown
+        );
+        assert_eq!(type_engine.are_equivalent(variable, u64_id), Ok(false));
+
+        // the engine is still usable afterwards
+        assert_eq!(unify_types(&session, variable, u32_id), Ok(()));
+        assert_eq!(type_engine.canonical_id(variable), Ok(Some(u32_id)));
+    }
+
+    #[test]
+    fn nested_snapshots_test() {
+        let type_engine = TypeEngine::default();
+        let u64_id = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let u32_id = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::ThirtyTwo));
+        let a = type_engine.insert_type(TypeInfo::Unknown);
+        let b = type_engine.insert_type(TypeInfo::Unknown);
+
+        // rolling back the inner snapshot keeps the changes of the outer one
+        let outer = type_engine.snapshot();
+        type_engine.unify_types(a, u64_id).unwrap();
+        let inner = type_engine.snapshot();
+        type_engine.unify_types(b, u32_id).unwrap();
+        type_engine.rollback_to(inner);
+        assert_eq!(type_engine.canonical_id(a), Ok(Some(u64_id)));
+        assert_eq!(type_engine.canonical_id(b), Ok(None));
+        type_engine.commit(outer);
+        assert_eq!(type_engine.canonical_id(a), Ok(Some(u64_id)));
+
+        // rolling back the outer snapshot undoes the committed inner one
+        let outer = type_engine.snapshot();
+        let inner = type_engine.snapshot();
+        type_engine.unify_types(b, u32_id).unwrap();
+        type_engine.commit(inner);
+        assert_eq!(type_engine.canonical_id(b), Ok(Some(u32_id)));
+        type_engine.rollback_to(outer);
+        assert_eq!(type_engine.canonical_id(b), Ok(None));
+        assert_eq!(type_engine.canonical_id(a), Ok(Some(u64_id)));
+    }
+
+    #[test]
+    #[should_panic(expected = "snapshots must be closed in the reverse order that they were taken")]
+    fn snapshots_closed_out_of_order_test() {
+        let type_engine = TypeEngine::default();
+        let outer = type_engine.snapshot();
+        let _inner = type_engine.snapshot();
+        type_engine.commit(outer);
+    }
+
+    #[test]
+    fn stale_type_id_test() {
+        let type_engine = TypeEngine::default();
+        let variable = type_engine.insert_type(TypeInfo::Unknown);
+        type_engine.clear();
+        let a = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let err = type_engine.look_up_type_id(variable).unwrap_err();
+        assert!(err.starts_with("stale id"), "{}", err);
+        assert_eq!(type_engine.unify_types(variable, a), Err(err.clone()));
+        assert_eq!(type_engine.resolve_type(variable).err(), Some(err));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn foreign_type_id_test() {
+        let type_engine = TypeEngine::default();
+        let other = TypeEngine::default();
+        let a = type_engine.insert_type(TypeInfo::UnsignedInteger(IntegerBits::SixtyFour));
+        let variable = other.insert_type(TypeInfo::Unknown);
+        let err = type_engine.unify_types(variable, a).unwrap_err();
+        assert!(err.contains("used with engine"), "{}", err);
+        assert!(type_engine.canonical_id(variable).is_err());
+    }
+
+    #[test]
+    fn display_without_active_session_test() {
+        let session = Session::new();
+        let type_id = insert_type(&session, TypeInfo::UnsignedInteger(IntegerBits::Eight));
+
+        // outside of the session, an id can only show its index
+        assert_eq!(type_id.to_string(), format!("<type #{}>", *type_id));
+        assert_eq!(session.enter(|| type_id.to_string()), "u8");
+    }
+}
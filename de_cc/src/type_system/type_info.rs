@@ -1,43 +1,45 @@
 use std::fmt;
-use std::hash::Hash;
-use std::hash::Hasher;
 
-use crate::language::ty::typed_declaration::TyStructField;
+use crate::declaration_engine::declaration_engine::de_struct_path;
+use crate::declaration_engine::declaration_id::DeclId;
+use crate::language::ty::typed_declaration::{TyStructDeclaration, TyStructField};
+use crate::session::with_active_session;
 
-use super::type_argument::TypeArgument;
-use super::type_engine::look_up_type_id;
 use super::type_parameter::TypeParameter;
-use super::{type_id::*, IntegerBits};
-
-#[derive(Clone)]
+use super::IntegerBits;
+
+/// The type information of a type.
+///
+/// Equality and hashing compare the types inside of a type by their ids, like
+/// [TypeId] does, so they do not look anything up. Use
+/// [are_equivalent_types](super::type_engine::are_equivalent_types) to compare
+/// the types that ids refer to.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
 pub enum TypeInfo {
     ErrorRecovery,
+    #[default]
     Unknown,
     UnknownGeneric {
         name: String,
     },
+    /// A type that is only known by its name, as it is written in the parsed
+    /// AST, which is resolved during type collection.
     Custom {
         name: String,
-        type_arguments: Vec<TypeArgument>,
+        type_arguments: Vec<TypeInfo>,
     },
     Unit,
-    Ref(TypeId),
     UnsignedInteger(IntegerBits),
+    /// A struct type, identified by the declaration it originates from and its
+    /// type arguments, rather than by its name and fields.
     Struct {
+        decl_id: DeclId<TyStructDeclaration>,
         name: String,
         type_parameters: Vec<TypeParameter>,
         fields: Vec<TyStructField>,
     },
 }
 
-impl Eq for TypeInfo {}
-
-impl Default for TypeInfo {
-    fn default() -> Self {
-        TypeInfo::Unknown
-    }
-}
-
 impl fmt::Display for TypeInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -67,17 +69,20 @@ impl fmt::Display for TypeInfo {
                 )
             }
             TypeInfo::UnsignedInteger(bits) => write!(f, "{}", bits),
-            TypeInfo::Ref(id) => write!(f, "{}", look_up_type_id(*id)),
             TypeInfo::Unit => write!(f, "()"),
             TypeInfo::Struct {
-                name,
+                decl_id,
                 type_parameters,
                 ..
             } => {
                 write!(
                     f,
                     "{}{}",
-                    name,
+                    match with_active_session(|session| de_struct_path(session, *decl_id)) {
+                        Some(Ok(path)) => path,
+                        Some(Err(err)) => format!("<{}>", err),
+                        None => decl_id.to_string(),
+                    },
                     if type_parameters.is_empty() {
                         "".to_string()
                     } else {
@@ -125,12 +130,12 @@ impl fmt::Debug for TypeInfo {
                 )
             }
             TypeInfo::UnsignedInteger(bits) => write!(f, "{}", bits),
-            TypeInfo::Ref(id) => write!(f, "ref..{}", **id),
             TypeInfo::Unit => write!(f, "()"),
             TypeInfo::Struct {
                 name,
                 type_parameters,
                 fields,
+                ..
             } => {
                 write!(
                     f,
@@ -150,7 +155,7 @@ impl fmt::Debug for TypeInfo {
                     },
                     fields
                         .iter()
-                        .map(|x| format!("{:?}", TypeInfo::Ref(x.type_id)))
+                        .map(|x| format!("ref..{}", *x.type_id))
                         .collect::<Vec<_>>()
                         .join(", ")
                 )
@@ -159,93 +164,8 @@ impl fmt::Debug for TypeInfo {
     }
 }
 
-impl Hash for TypeInfo {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            TypeInfo::ErrorRecovery => {
-                state.write_u8(0);
-            }
-            TypeInfo::Unknown => {
-                state.write_u8(1);
-            }
-            TypeInfo::UnknownGeneric { name } => {
-                state.write_u8(2);
-                name.hash(state);
-            }
-            TypeInfo::UnsignedInteger(bits) => {
-                state.write_u8(3);
-                bits.hash(state);
-            }
-            TypeInfo::Ref(id) => {
-                state.write_u8(4);
-                id.hash(state);
-            }
-            TypeInfo::Unit => {
-                state.write_u8(5);
-            }
-            TypeInfo::Struct {
-                name,
-                type_parameters,
-                fields,
-            } => {
-                state.write_u8(6);
-                name.hash(state);
-                type_parameters.hash(state);
-                fields.hash(state);
-            }
-            TypeInfo::Custom {
-                name,
-                type_arguments,
-            } => {
-                state.write_u8(7);
-                name.hash(state);
-                type_arguments.hash(state);
-            }
-        }
-    }
-}
-
-impl PartialEq for TypeInfo {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (TypeInfo::Unknown, TypeInfo::Unknown) => true,
-            (
-                TypeInfo::UnknownGeneric { name: l_name },
-                TypeInfo::UnknownGeneric { name: r_name },
-            ) => l_name == r_name,
-            (TypeInfo::UnsignedInteger(l), TypeInfo::UnsignedInteger(r)) => l == r,
-            (TypeInfo::Ref(l), TypeInfo::Ref(r)) => look_up_type_id(*l) == look_up_type_id(*r),
-            (TypeInfo::ErrorRecovery, TypeInfo::ErrorRecovery) => todo!(),
-            (
-                TypeInfo::Custom {
-                    name: l_name,
-                    type_arguments: l_type_args,
-                },
-                TypeInfo::Custom {
-                    name: r_name,
-                    type_arguments: r_type_args,
-                },
-            ) => l_name == r_name && l_type_args == r_type_args,
-            (TypeInfo::Unit, TypeInfo::Unit) => true,
-            (
-                TypeInfo::Struct {
-                    name: l_name,
-                    fields: l_fields,
-                    type_parameters: l_type_parameters,
-                },
-                TypeInfo::Struct {
-                    name: r_name,
-                    fields: r_fields,
-                    type_parameters: r_type_parameters,
-                },
-            ) => l_name == r_name && l_fields == r_fields && l_type_parameters == r_type_parameters,
-            _ => false,
-        }
-    }
-}
-
 pub mod constructors {
-    use crate::type_system::{type_argument::TypeArgument, type_engine::insert_type, IntegerBits};
+    use crate::type_system::IntegerBits;
 
     use super::TypeInfo;
 
@@ -258,13 +178,7 @@ pub mod constructors {
     pub fn t_cus_(name: &str, type_arguments: &[TypeInfo]) -> TypeInfo {
         TypeInfo::Custom {
             name: name.to_string(),
-            type_arguments: type_arguments
-                .iter()
-                .cloned()
-                .map(|type_info| TypeArgument {
-                    type_id: insert_type(type_info),
-                })
-                .collect(),
+            type_arguments: type_arguments.to_vec(),
         }
     }
 
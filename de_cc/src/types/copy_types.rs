@@ -1,9 +1,10 @@
 use either::Either;
 
+use crate::session::Session;
 use crate::type_system::type_mapping::TypeMapping;
 
 pub(crate) trait CopyTypes {
-    fn copy_types(&mut self, type_mapping: &TypeMapping);
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping);
 }
 
 impl<L, R> CopyTypes for Either<L, R>
@@ -11,10 +12,10 @@ where
     L: CopyTypes,
     R: CopyTypes,
 {
-    fn copy_types(&mut self, type_mapping: &TypeMapping) {
+    fn copy_types(&mut self, session: &Session, type_mapping: &TypeMapping) {
         match self {
-            Either::Left(l) => l.copy_types(type_mapping),
-            Either::Right(r) => r.copy_types(type_mapping),
+            Either::Left(l) => l.copy_types(session, type_mapping),
+            Either::Right(r) => r.copy_types(session, type_mapping),
         }
     }
 }
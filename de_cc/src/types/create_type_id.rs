@@ -1,5 +1,9 @@
+use crate::declaration_engine::declaration_id::DeclId;
+use crate::session::Session;
 use crate::type_system::type_id::TypeId;
 
-pub(crate) trait CreateTypeId {
-    fn create_type_id(&self) -> TypeId;
+pub(crate) trait CreateTypeId: Sized {
+    /// Inserts the type of the declaration `decl_id`, which `self` is the
+    /// declaration or a monomorphized copy of.
+    fn create_type_id(&self, session: &Session, decl_id: DeclId<Self>) -> TypeId;
 }
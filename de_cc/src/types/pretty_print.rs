@@ -1,5 +1,6 @@
 use crate::collection_context::collection_context::CollectionContext;
 
+#[allow(dead_code)]
 pub(crate) trait PrettyPrint {
     fn pretty_print(&self, cc: &CollectionContext) -> String;
     fn pretty_print_debug(&self, cc: &CollectionContext) -> String;
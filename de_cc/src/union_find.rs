@@ -0,0 +1,130 @@
+/// A disjoint-set forest over the indices `0..len`, with path compression and
+/// union by rank, so that [UnionFind::find] and [UnionFind::union] take
+/// near-constant amortized time.
+///
+/// While a snapshot is open, every write to the forest is recorded so that it
+/// can be undone with [UnionFind::rollback_to].
+#[derive(Default, Debug)]
+pub(crate) struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+    /// The previous parent and rank of every index written to since the oldest
+    /// open snapshot.
+    undo_log: Vec<(usize, usize, u8)>,
+    open_snapshots: usize,
+}
+
+impl UnionFind {
+    /// Adds a new set containing only the next index, and returns that index.
+    pub(crate) fn push(&mut self) -> usize {
+        let index = self.parents.len();
+        self.parents.push(index);
+        self.ranks.push(0);
+        index
+    }
+
+    /// Returns the representative of the set containing `index`, pointing every
+    /// index on the way directly at it.
+    pub(crate) fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut current = index;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.set(current, root, self.ranks[current]);
+            current = next;
+        }
+        root
+    }
+
+    /// Merges the sets containing `a` and `b`, and returns the representative
+    /// of the merged set.
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> usize {
+        let root = self.union_root(a, b);
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+        let child = if root == a { b } else { a };
+        self.set(child, root, self.ranks[child]);
+        if self.ranks[root] == self.ranks[child] {
+            self.set(root, root, self.ranks[root] + 1);
+        }
+        root
+    }
+
+    /// Returns the representative that the merged set of `a` and `b` would have,
+    /// without merging them.
+    pub(crate) fn union_root(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        if self.ranks[a] < self.ranks[b] {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// Starts recording writes, and returns the position in the undo log to roll
+    /// back to.
+    pub(crate) fn snapshot(&mut self) -> usize {
+        self.open_snapshots += 1;
+        self.undo_log.len()
+    }
+
+    /// Undoes every write since the snapshot at `position` was taken, and closes
+    /// that snapshot.
+    ///
+    /// Indices pushed since then are kept, as singleton sets.
+    pub(crate) fn rollback_to(&mut self, position: usize) {
+        self.assert_open(position);
+        while self.undo_log.len() > position {
+            let (index, parent, rank) = self.undo_log.pop().unwrap();
+            self.parents[index] = parent;
+            self.ranks[index] = rank;
+        }
+        self.close_snapshot();
+    }
+
+    /// Keeps every write since the snapshot at `position` was taken, and closes
+    /// that snapshot.
+    pub(crate) fn commit(&mut self, position: usize) {
+        self.assert_open(position);
+        self.close_snapshot();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.parents.clear();
+        self.ranks.clear();
+        self.undo_log.clear();
+        self.open_snapshots = 0;
+    }
+
+    fn set(&mut self, index: usize, parent: usize, rank: u8) {
+        if self.open_snapshots > 0 {
+            self.undo_log
+                .push((index, self.parents[index], self.ranks[index]));
+        }
+        self.parents[index] = parent;
+        self.ranks[index] = rank;
+    }
+
+    /// Checks that `position` is from a snapshot that is still open, as the
+    /// writes since it would otherwise have been cleared from the undo log.
+    fn assert_open(&self, position: usize) {
+        assert!(
+            self.open_snapshots > 0 && position <= self.undo_log.len(),
+            "the snapshot at position {} of the undo log is no longer open",
+            position
+        );
+    }
+
+    fn close_snapshot(&mut self) {
+        self.open_snapshots -= 1;
+        // nothing can be rolled back once the outermost snapshot is closed
+        if self.open_snapshots == 0 {
+            self.undo_log.clear();
+        }
+    }
+}
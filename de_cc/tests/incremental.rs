@@ -0,0 +1,153 @@
+use de_cc::{
+    compile,
+    language::parsed::{
+        constructors::*, declaration::constructors::*, expression::constructors::*, Application,
+        File, Node,
+    },
+    type_system::type_info::constructors::*,
+    IncrementalCompiler, Session,
+};
+
+use crate::helpers::{handle_u64_decl, handle_u64_impl};
+
+mod helpers;
+
+const VERSIONS: usize = 9;
+
+/// Builds the `version`th edit of a small application split over several files.
+fn version(version: usize) -> Application {
+    let five = if version == 0 || version == 8 {
+        5u64
+    } else {
+        6u64
+    };
+    let handle_value = if (6..8).contains(&version) { 100 } else { 99 };
+
+    let mut bob_nodes = vec![
+        handle_u64_decl(),
+        struct_("Data", &[], &[struct_field("a", t_u8())]),
+        struct_(
+            "Point",
+            &[type_param("T", None)],
+            &[struct_field("x", t_gen_("T"))],
+        ),
+        handle_u64_impl(t_cus_("Data", &[]), handle_value),
+        func_decl(
+            "id",
+            &[type_param("T", None)],
+            &[func_param("x", t_gen_("T"))],
+            &[return_(var("x"))],
+            t_gen_("T"),
+        ),
+        func_decl("five", &[], &[], &[return_(u64(five))], t_u64()),
+    ];
+    if version == 3 || version == 4 {
+        bob_nodes.push(func_decl("unused", &[], &[], &[], t_unit()));
+    }
+
+    let main_fn = func_decl(
+        "main",
+        &[],
+        &[],
+        &[
+            var_decl("a", None, func_app("id", &[], &[u32(1u32)])),
+            var_decl("b", None, func_app("five", &[], &[])),
+            var_decl(
+                "p",
+                None,
+                struct_exp("Point", &[], &[struct_exp_field("x", u8(2u8))]),
+            ),
+            var_decl(
+                "d",
+                None,
+                struct_exp("Data", &[], &[struct_exp_field("a", u8(3u8))]),
+            ),
+            var_decl(
+                "h",
+                None,
+                method_app("d", "handle_u64_fn", &[], &[u64(1u64)]),
+            ),
+        ],
+        t_unit(),
+    );
+    let other_fn = func_decl(
+        "other",
+        &[],
+        &[],
+        &[
+            var_decl("c", None, func_app("id", &[], &[u64(3u64)])),
+            var_decl(
+                "q",
+                None,
+                struct_exp("Point", &[], &[struct_exp_field("x", u16(4u16))]),
+            ),
+        ],
+        t_unit(),
+    );
+    let shadowing_fn = func_decl("five", &[], &[], &[return_(u32(5u32))], t_u32());
+
+    let mut alice_nodes = vec![star_import("bob.sw")];
+    if (5..8).contains(&version) {
+        alice_nodes.push(star_import("carol.sw"));
+    }
+    match version {
+        0 | 1 | 8 => alice_nodes.push(main_fn),
+        2 => alice_nodes.extend([main_fn, other_fn]),
+        3 => alice_nodes.extend([main_fn, other_fn, shadowing_fn]),
+        4..=6 => alice_nodes.extend([main_fn, shadowing_fn]),
+        7 => alice_nodes.extend([shadowing_fn, main_fn]),
+        _ => unreachable!(),
+    }
+
+    let mut files = vec![
+        File {
+            name: "alice.sw".to_string(),
+            nodes: alice_nodes,
+        },
+        File {
+            name: "bob.sw".to_string(),
+            nodes: bob_nodes,
+        },
+    ];
+    if (5..8).contains(&version) {
+        files.push(File {
+            name: "carol.sw".to_string(),
+            nodes: carol_nodes(),
+        });
+    }
+    Application { files }
+}
+
+fn carol_nodes() -> Vec<Node> {
+    vec![
+        star_import("bob.sw"),
+        func_decl(
+            "carol_fn",
+            &[],
+            &[],
+            &[var_decl("e", None, func_app("id", &[], &[u8(7u8)]))],
+            t_unit(),
+        ),
+    ]
+}
+
+#[test]
+fn incremental_matches_from_scratch_test() {
+    println!(
+        "\n\n**********************************************************************************"
+    );
+
+    let mut compiler = IncrementalCompiler::new();
+    let incremental = (0..VERSIONS)
+        .map(|i| compiler.compile(version(i)).unwrap().to_string())
+        .collect::<Vec<_>>();
+    let from_scratch = (0..VERSIONS)
+        .map(|i| compile(&Session::new(), version(i)).unwrap().to_string())
+        .collect::<Vec<_>>();
+
+    for (i, (incremental, from_scratch)) in incremental.iter().zip(from_scratch.iter()).enumerate()
+    {
+        println!("{}", incremental);
+        assert_eq!(incremental, from_scratch, "version {} differs", i);
+    }
+}
@@ -0,0 +1,131 @@
+use std::thread;
+
+use de_cc::{
+    compile,
+    language::parsed::{
+        constructors::*, declaration::constructors::*, expression::constructors::*, Application,
+        File,
+    },
+    type_system::type_info::constructors::*,
+    Session,
+};
+
+use crate::helpers::{handle_u64_decl, handle_u64_impl};
+
+mod helpers;
+
+const THREADS: usize = 8;
+
+fn application() -> Application {
+    let data_decl = struct_(
+        "Data",
+        &[type_param("T", None)],
+        &[struct_field("value", t_gen_("T"))],
+    );
+    let id_fn = func_decl(
+        "id",
+        &[type_param("T", None)],
+        &[func_param("x", t_gen_("T"))],
+        &[return_(var("x"))],
+        t_gen_("T"),
+    );
+    let main_fn = func_decl(
+        "main",
+        &[],
+        &[],
+        &[
+            var_decl("a", None, func_app("id", &[], &[u8(1u8)])),
+            var_decl("b", None, func_app("id", &[], &[u64(2u64)])),
+            var_decl(
+                "c",
+                None,
+                struct_exp("Data", &[], &[struct_exp_field("value", u32(3u32))]),
+            ),
+            var_decl(
+                "d",
+                None,
+                method_app("c", "handle_u64_fn", &[], &[var("b")]),
+            ),
+        ],
+        t_unit(),
+    );
+    let program_1 = File {
+        name: "bob.sw".to_string(),
+        nodes: vec![
+            handle_u64_decl(),
+            data_decl,
+            handle_u64_impl(t_cus_("Data", &[t_u32()]), 4),
+            id_fn,
+            main_fn,
+        ],
+    };
+    Application {
+        files: vec![program_1],
+    }
+}
+
+#[test]
+fn concurrent_sessions_test() {
+    println!(
+        "\n\n**********************************************************************************"
+    );
+
+    let session = Session::new();
+    let expected = compile(&session, application()).unwrap().to_string();
+    println!("{}", expected);
+
+    // every thread compiles in its own session, at the same time as the others
+    let resolved_applications = thread::scope(|scope| {
+        let handles = (0..THREADS)
+            .map(|_| {
+                scope.spawn(|| {
+                    let session = Session::new();
+                    compile(&session, application()).unwrap().to_string()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    for resolved_application in resolved_applications.iter() {
+        assert_eq!(resolved_application, &expected);
+    }
+}
+
+#[test]
+fn cleared_session_test() {
+    println!(
+        "\n\n**********************************************************************************"
+    );
+
+    let session = Session::new();
+    let expected = compile(&session, application()).unwrap().to_string();
+
+    // a cleared session compiles a new application just like a new session
+    session.clear();
+    let resolved_application = compile(&session, application()).unwrap().to_string();
+    println!("{}", resolved_application);
+    assert_eq!(resolved_application, expected);
+}
+
+#[test]
+fn application_outlives_session_test() {
+    println!(
+        "\n\n**********************************************************************************"
+    );
+
+    let session = Session::new();
+    let expected = compile(&session, application()).unwrap().to_string();
+
+    // an application does not refer to anything in a session, so it can be
+    // built once and compiled in a cleared session or in another session
+    let application = application();
+    session.clear();
+    let resolved_application = compile(&session, application.clone()).unwrap().to_string();
+    assert_eq!(resolved_application, expected);
+    let resolved_application = compile(&Session::new(), application).unwrap().to_string();
+    assert_eq!(resolved_application, expected);
+}